@@ -0,0 +1,246 @@
+// A doubly linked list whose nodes live in a Vec (the arena) and link to each other by index.
+// There is no Rc or RefCell, so there is no ref-counting and no runtime borrow check:
+// iterators borrow the list, and the borrow checker makes sure nothing is popped under them.
+// Freed slots are reused, and each slot carries a generation that is bumped on removal,
+// so a Handle to a removed node can never reach whatever is stored in that slot later.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Handle {
+    index: usize,
+    generation: u64,
+}
+
+struct Node<T> {
+    value: T,
+    next: Option<usize>,
+    prev: Option<usize>,
+}
+
+enum Slot<T> {
+    Occupied(Node<T>),
+    Free { next_free: Option<usize> },
+}
+
+struct Entry<T> {
+    generation: u64,
+    slot: Slot<T>,
+}
+
+pub struct TransactionLog<T> {
+    entries: Vec<Entry<T>>,
+    free: Option<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    pub length: u64,
+}
+
+impl<T> TransactionLog<T> {
+    pub fn new_empty() -> TransactionLog<T> {
+        TransactionLog {
+            entries: vec![],
+            free: None,
+            head: None,
+            tail: None,
+            length: 0,
+        }
+    }
+
+    pub fn append(&mut self, value: T) -> Handle {
+        let index = self.alloc(Node {
+            value,
+            next: None,
+            prev: self.tail,
+        });
+        match self.tail {
+            Some(old) => self.node_mut(old).next = Some(index),
+            None => self.head = Some(index),
+        };
+        self.tail = Some(index);
+        self.length += 1;
+        Handle {
+            index,
+            generation: self.entries[index].generation,
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.head.map(|head| self.unlink(head))
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        match self.entries.get(handle.index) {
+            Some(Entry {
+                generation,
+                slot: Slot::Occupied(node),
+            }) if *generation == handle.generation => Some(&node.value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.entries.get_mut(handle.index) {
+            Some(Entry {
+                generation,
+                slot: Slot::Occupied(node),
+            }) if *generation == handle.generation => Some(&mut node.value),
+            _ => None,
+        }
+    }
+
+    // O(1) removal from anywhere in the list, stale handles are ignored
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.get(handle)?;
+        Some(self.unlink(handle.index))
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        ListIterator {
+            list: self,
+            front: self.head,
+            back: self.tail,
+            remaining: self.length,
+        }
+    }
+
+    // the iterator is double ended, so walking backwards from the tail is `back_iter().rev()`
+    pub fn back_iter(&self) -> ListIterator<'_, T> {
+        self.iter()
+    }
+
+    fn alloc(&mut self, node: Node<T>) -> usize {
+        match self.free {
+            Some(index) => {
+                let entry = &mut self.entries[index];
+                if let Slot::Free { next_free } = entry.slot {
+                    self.free = next_free;
+                }
+                entry.slot = Slot::Occupied(node);
+                index
+            }
+            None => {
+                self.entries.push(Entry {
+                    generation: 0,
+                    slot: Slot::Occupied(node),
+                });
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    fn unlink(&mut self, index: usize) -> T {
+        let entry = &mut self.entries[index];
+        entry.generation += 1;
+        let slot = std::mem::replace(
+            &mut entry.slot,
+            Slot::Free {
+                next_free: self.free,
+            },
+        );
+        self.free = Some(index);
+        let node = match slot {
+            Slot::Occupied(node) => node,
+            Slot::Free { .. } => unreachable!("linked node is in a free slot"),
+        };
+        match node.prev {
+            Some(prev) => self.node_mut(prev).next = node.next,
+            None => self.head = node.next,
+        };
+        match node.next {
+            Some(next) => self.node_mut(next).prev = node.prev,
+            None => self.tail = node.prev,
+        };
+        self.length -= 1;
+        node.value
+    }
+
+    fn node(&self, index: usize) -> &Node<T> {
+        match self.entries[index].slot {
+            Slot::Occupied(ref node) => node,
+            Slot::Free { .. } => unreachable!("linked node is in a free slot"),
+        }
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<T> {
+        match self.entries[index].slot {
+            Slot::Occupied(ref mut node) => node,
+            Slot::Free { .. } => unreachable!("linked node is in a free slot"),
+        }
+    }
+}
+
+pub struct ListIterator<'a, T> {
+    list: &'a TransactionLog<T>,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: u64,
+}
+
+impl<'a, T> Iterator for ListIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.front.map(|index| {
+            let node = self.list.node(index);
+            self.front = node.next;
+            self.remaining -= 1;
+            &node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ListIterator<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.back.map(|index| {
+            let node = self.list.node(index);
+            self.back = node.prev;
+            self.remaining -= 1;
+            &node.value
+        })
+    }
+}
+
+impl<'a, T> IntoIterator for &'a TransactionLog<T> {
+    type Item = &'a T;
+    type IntoIter = ListIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<T> {
+    list: TransactionLog<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.tail.map(|tail| self.list.unlink(tail))
+    }
+}
+
+impl<T> IntoIterator for TransactionLog<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
@@ -0,0 +1,229 @@
+// ref
+// Pelkonen et al., "Gorilla: A Fast, Scalable, In-Memory Time Series Database", section 4.1.1
+//
+// Timestamps are split into blocks of BLOCK_SIZE. The index keeps the first timestamp of every
+// block and where its bytes start, the rest of a block is stored as delta-of-deltas: for evenly
+// spaced timestamps (t[i] - t[i-1]) - (t[i-1] - t[i-2]) is 0 or close to it. Each of them is
+// zigzag encoded, so small negative values stay small, and written as a LEB128 varint, which is a
+// single byte for anything in -64..64 instead of the 8 bytes of a u64.
+// All arithmetic wraps, so timestamps that jump around still come back out exactly.
+//
+// `at` decodes from the start of the block, so it is O(BLOCK_SIZE), and appending only needs the
+// last timestamp and delta, so it is amortized O(1).
+
+const BLOCK_SIZE: usize = 128;
+
+struct Block {
+    first: u64,
+    // start of the block's delta-of-deltas in `data`
+    offset: usize,
+}
+
+pub struct CompressedTimestamps {
+    index: Vec<Block>,
+    data: Vec<u8>,
+    length: usize,
+    // state of the encoder at the end of the last block
+    last: u64,
+    last_delta: u64,
+}
+
+impl CompressedTimestamps {
+    pub fn new_empty() -> CompressedTimestamps {
+        CompressedTimestamps {
+            index: vec![],
+            data: vec![],
+            length: 0,
+            last: 0,
+            last_delta: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn append(&mut self, timestamp: u64) {
+        if self.length.is_multiple_of(BLOCK_SIZE) {
+            self.index.push(Block {
+                first: timestamp,
+                offset: self.data.len(),
+            });
+            self.last_delta = 0;
+        } else {
+            let delta = timestamp.wrapping_sub(self.last);
+            write_varint(&mut self.data, zigzag(delta.wrapping_sub(self.last_delta)));
+            self.last_delta = delta;
+        }
+        self.last = timestamp;
+        self.length += 1;
+    }
+
+    pub fn at(&self, index: usize) -> Option<u64> {
+        if index >= self.length {
+            return None;
+        }
+        self.block_iter(index / BLOCK_SIZE).nth(index % BLOCK_SIZE)
+    }
+
+    pub fn last(&self) -> Option<u64> {
+        if self.length == 0 {
+            None
+        } else {
+            Some(self.last)
+        }
+    }
+
+    pub fn iter(&self) -> TimestampIterator<'_> {
+        TimestampIterator {
+            column: self,
+            block: self.block_iter(0),
+            next_block: 1,
+            remaining: self.length,
+        }
+    }
+
+    // bytes used for the timestamps, compare with len() * 8 for a plain array
+    pub fn compressed_bytes(&self) -> usize {
+        self.data.len() + self.index.len() * std::mem::size_of::<Block>()
+    }
+
+    fn block_iter(&self, block: usize) -> BlockDecoder<'_> {
+        match self.index.get(block) {
+            Some(b) => BlockDecoder {
+                data: &self.data[b.offset..],
+                next: Some(b.first),
+                delta: 0,
+                remaining: block_len(self.length, block),
+            },
+            None => BlockDecoder {
+                data: &[],
+                next: None,
+                delta: 0,
+                remaining: 0,
+            },
+        }
+    }
+}
+
+impl Default for CompressedTimestamps {
+    fn default() -> CompressedTimestamps {
+        CompressedTimestamps::new_empty()
+    }
+}
+
+impl FromIterator<u64> for CompressedTimestamps {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> CompressedTimestamps {
+        let mut column = CompressedTimestamps::new_empty();
+        for timestamp in iter {
+            column.append(timestamp);
+        }
+        column
+    }
+}
+
+impl<'a> IntoIterator for &'a CompressedTimestamps {
+    type Item = u64;
+    type IntoIter = TimestampIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// number of timestamps in `block`, only the last block can be partly filled
+fn block_len(length: usize, block: usize) -> usize {
+    (length - block * BLOCK_SIZE).min(BLOCK_SIZE)
+}
+
+fn zigzag(value: u64) -> u64 {
+    let value = value as i64;
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> u64 {
+    (value >> 1) ^ (value & 1).wrapping_neg()
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+// returns the value and the number of bytes it took
+fn read_varint(data: &[u8]) -> (u64, usize) {
+    let mut value = 0;
+    for (i, byte) in data.iter().enumerate() {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+    panic!("truncated varint in timestamp block");
+}
+
+// the timestamps of one block, decoded one after the other
+struct BlockDecoder<'a> {
+    data: &'a [u8],
+    next: Option<u64>,
+    delta: u64,
+    remaining: usize,
+}
+
+impl<'a> Iterator for BlockDecoder<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let current = self.next?;
+        self.remaining -= 1;
+        self.next = if self.remaining == 0 {
+            None
+        } else {
+            let (encoded, size) = read_varint(self.data);
+            self.data = &self.data[size..];
+            self.delta = self.delta.wrapping_add(unzigzag(encoded));
+            Some(current.wrapping_add(self.delta))
+        };
+        Some(current)
+    }
+}
+
+pub struct TimestampIterator<'a> {
+    column: &'a CompressedTimestamps,
+    block: BlockDecoder<'a>,
+    next_block: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for TimestampIterator<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let timestamp = match self.block.next() {
+            Some(timestamp) => timestamp,
+            None => {
+                self.block = self.column.block_iter(self.next_block);
+                self.next_block += 1;
+                self.block.next()?
+            }
+        };
+        self.remaining -= 1;
+        Some(timestamp)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for TimestampIterator<'a> {}
@@ -0,0 +1,184 @@
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// ref
+// Michael & Scott, "Simple, Fast, and Practical Non-Blocking and Blocking Concurrent Queue Algorithms"
+// (the two-lock queue), with the blocking and capacity handling of Java's LinkedBlockingQueue.
+//
+// The list always starts with a dummy node. Producers only ever touch the tail and consumers only the
+// head, each side under its own lock, so appends and pops don't wait for each other. The shared
+// `length` is what both sides wait on: a consumer sleeps while it is 0, a producer while it is at
+// capacity, and whoever changes it from/to such a value wakes the other side up.
+
+struct Node<T> {
+    value: Option<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(value: Option<T>) -> *mut Node<T> {
+        Box::into_raw(Box::new(Node {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+struct Link<T>(*mut Node<T>);
+
+pub struct ConcurrentTransactionLog<T> {
+    head: Mutex<Link<T>>,
+    tail: Mutex<Link<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    length: AtomicUsize,
+    capacity: usize,
+}
+
+// the raw links are only followed while holding the lock of their end of the list
+unsafe impl<T: Send> Send for ConcurrentTransactionLog<T> {}
+unsafe impl<T: Send> Sync for ConcurrentTransactionLog<T> {}
+
+impl<T> ConcurrentTransactionLog<T> {
+    pub fn new_empty() -> ConcurrentTransactionLog<T> {
+        ConcurrentTransactionLog::new_bounded(usize::MAX)
+    }
+
+    // `append` blocks while `capacity` values are waiting to be popped
+    pub fn new_bounded(capacity: usize) -> ConcurrentTransactionLog<T> {
+        assert!(capacity > 0, "capacity has to be at least 1");
+        let dummy = Node::new(None);
+        ConcurrentTransactionLog {
+            head: Mutex::new(Link(dummy)),
+            tail: Mutex::new(Link(dummy)),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            length: AtomicUsize::new(0),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.length.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn append(&self, value: T) {
+        let mut tail = lock(&self.tail);
+        while self.length.load(Ordering::SeqCst) == self.capacity {
+            tail = self
+                .not_full
+                .wait(tail)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        self.enqueue(tail, value);
+    }
+
+    // like `append`, but hands the value back instead of waiting for room
+    pub fn try_append(&self, value: T) -> Result<(), T> {
+        let tail = lock(&self.tail);
+        if self.length.load(Ordering::SeqCst) == self.capacity {
+            return Err(value);
+        }
+        self.enqueue(tail, value);
+        Ok(())
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let head = lock(&self.head);
+        if self.length.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        Some(self.dequeue(head))
+    }
+
+    pub fn pop_wait(&self) -> T {
+        let mut head = lock(&self.head);
+        while self.length.load(Ordering::SeqCst) == 0 {
+            head = self
+                .not_empty
+                .wait(head)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        self.dequeue(head)
+    }
+
+    pub fn pop_wait_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut head = lock(&self.head);
+        while self.length.load(Ordering::SeqCst) == 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            head = self
+                .not_empty
+                .wait_timeout(head, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        Some(self.dequeue(head))
+    }
+
+    fn enqueue(&self, mut tail: MutexGuard<'_, Link<T>>, value: T) {
+        let new = Node::new(Some(value));
+        unsafe { (*tail.0).next.store(new, Ordering::Release) };
+        tail.0 = new;
+        let before = self.length.fetch_add(1, Ordering::SeqCst);
+        if before + 1 < self.capacity {
+            // there is still room, pass the wake up on to the next waiting producer
+            self.not_full.notify_one();
+        }
+        drop(tail);
+        if before == 0 {
+            let _head = lock(&self.head);
+            self.not_empty.notify_one();
+        }
+    }
+
+    // the caller has checked that `length` is not 0, so there is a node after the dummy
+    fn dequeue(&self, mut head: MutexGuard<'_, Link<T>>) -> T {
+        let dummy = head.0;
+        let next = unsafe { (*dummy).next.load(Ordering::Acquire) };
+        // the first real node becomes the new dummy
+        let value = unsafe { (*next).value.take() }.expect("only the dummy node is empty");
+        head.0 = next;
+        unsafe { drop(Box::from_raw(dummy)) };
+        let before = self.length.fetch_sub(1, Ordering::SeqCst);
+        if before > 1 {
+            self.not_empty.notify_one();
+        }
+        drop(head);
+        if before == self.capacity {
+            let _tail = lock(&self.tail);
+            self.not_full.notify_one();
+        }
+        value
+    }
+}
+
+impl<T> Drop for ConcurrentTransactionLog<T> {
+    fn drop(&mut self) {
+        let mut current = lock(&self.head).0;
+        while !current.is_null() {
+            let node = unsafe { Box::from_raw(current) };
+            current = node.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+// a panicking producer or consumer leaves the list itself intact, so poisoning is ignored
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
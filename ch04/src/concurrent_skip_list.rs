@@ -0,0 +1,441 @@
+use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned, Shared};
+use rand::rngs::SmallRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+// ref
+// Herlihy & Shavit, "The Art of Multiprocessor Programming", chapter 14.4 (LockFreeSkipList)
+//
+// Removing an entry takes its value out of the node with a CAS, whoever swaps in null has removed
+// it, and an insert that finds a null value knows the node is on its way out. Then the forward
+// links of the node are marked (tag bit 1), top level first, by the remover or by any insert that
+// ran into the node. Any thread that walks past a marked node unlinks it from its predecessor with
+// a CAS, that is how `search` helps out.
+// Memory is reclaimed with epochs: a node is only destroyed after it was unlinked from every level
+// it was linked on, and epoch::Guard makes sure no thread still looking at it gets a freed node.
+
+struct Node<K, V> {
+    key: K,
+    // swapped as a whole when an existing key gets a new value, null once the entry is removed
+    value: Atomic<V>,
+    next: Box<[Atomic<Node<K, V>>]>,
+    // levels this node is linked on, plus one while `insert` is still linking it
+    linked: AtomicUsize,
+}
+
+impl<K, V> Drop for Node<K, V> {
+    fn drop(&mut self) {
+        // the node is unreachable by now, so its current value is too
+        unsafe {
+            let value = self.value.load(Ordering::Relaxed, epoch::unprotected());
+            if !value.is_null() {
+                drop(value.into_owned());
+            }
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    // the value, unless the entry has been removed
+    fn live_value<'g>(&self, guard: &'g Guard) -> Option<&'g V> {
+        // links are only marked after the value is gone
+        unsafe { self.value.load(Ordering::Acquire, guard).as_ref() }
+    }
+
+    // marks the links of a node whose value has been taken, so searches unlink it
+    fn mark(&self, guard: &Guard) {
+        for level in (0..self.next.len()).rev() {
+            self.next[level].fetch_or(1, Ordering::AcqRel, guard);
+        }
+    }
+}
+
+pub struct ConcurrentSkipList<K, V> {
+    head: Box<[Atomic<Node<K, V>>]>,
+    max_level: usize,
+    // chance that a node is promoted to the next level
+    promotion: f64,
+    // every insert seeds its own generator with the next number, so they never wait on each other
+    seed: AtomicU64,
+    length: AtomicUsize,
+}
+
+// where a key belongs on every level: the links to swing and the nodes they point to now
+struct Position<'g, K, V> {
+    preds: Vec<&'g [Atomic<Node<K, V>>]>,
+    succs: Vec<Shared<'g, Node<K, V>>>,
+}
+
+impl<'g, K, V> Position<'g, K, V> {
+    fn found(&self, key: &K) -> Option<&'g Node<K, V>>
+    where
+        K: Ord,
+    {
+        unsafe { self.succs[0].as_ref() }.filter(|node| node.key == *key)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ConcurrentSkipList<K, V> {
+    pub fn new_empty(max_level: usize) -> ConcurrentSkipList<K, V> {
+        ConcurrentSkipList::new_seeded(max_level, 0.5, thread_rng().gen())
+    }
+
+    // the same seed and inserts from a single thread always build the same levels
+    pub fn new_seeded(max_level: usize, promotion: f64, seed: u64) -> ConcurrentSkipList<K, V> {
+        assert!(
+            promotion > 0.0 && promotion < 1.0,
+            "promotion probability has to be between 0 and 1"
+        );
+        ConcurrentSkipList {
+            head: (0..=max_level).map(|_| Atomic::null()).collect(),
+            max_level,
+            promotion,
+            seed: AtomicU64::new(seed),
+            length: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // inserts at the right position for `key`, an existing value for the key is replaced and returned
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let guard = &epoch::pin();
+        let height = 1 + self.get_level();
+        // the value only goes into the new node right before that is linked
+        let value = Owned::new(value).into_shared(guard);
+        let mut new = Owned::new(Node {
+            key: key.clone(),
+            value: Atomic::null(),
+            next: (0..height).map(|_| Atomic::null()).collect(),
+            // the base level link and the insert itself
+            linked: AtomicUsize::new(2),
+        });
+
+        let mut position = self.search(&key, guard);
+        let node = loop {
+            if let Some(found) = position.found(&key) {
+                let old = found.value.load(Ordering::Acquire, guard);
+                if old.is_null() {
+                    // being removed, get it out of the way and insert a new node instead
+                    found.mark(guard);
+                    position = self.search(&key, guard);
+                    continue;
+                }
+                if found
+                    .value
+                    .compare_exchange(old, value, Ordering::AcqRel, Ordering::Acquire, guard)
+                    .is_err()
+                {
+                    continue;
+                }
+                // the unused node must not free the value it handed over
+                new.value = Atomic::null();
+                let old_value = unsafe { old.deref() }.clone();
+                unsafe { guard.defer_destroy(old) };
+                return Some(old_value);
+            }
+            new.value = Atomic::from(value);
+            new.next[0].store(position.succs[0], Ordering::Relaxed);
+            match position.preds[0][0].compare_exchange(
+                position.succs[0],
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                guard,
+            ) {
+                Ok(node) => break node,
+                Err(err) => {
+                    new = err.new;
+                    position = self.search(&key, guard);
+                }
+            }
+        };
+        self.length.fetch_add(1, Ordering::SeqCst);
+
+        let node_ref = unsafe { node.deref() };
+        'levels: for level in 1..height {
+            loop {
+                let succ = position.succs[level];
+                let current = node_ref.next[level].load(Ordering::Acquire, guard);
+                // a marked link means the node has been removed in the meantime
+                if current.tag() == 1
+                    || node_ref.next[level]
+                        .compare_exchange(current, succ, Ordering::AcqRel, Ordering::Acquire, guard)
+                        .is_err()
+                {
+                    break 'levels;
+                }
+                node_ref.linked.fetch_add(1, Ordering::AcqRel);
+                if position.preds[level][level]
+                    .compare_exchange(succ, node, Ordering::AcqRel, Ordering::Acquire, guard)
+                    .is_ok()
+                {
+                    break;
+                }
+                node_ref.linked.fetch_sub(1, Ordering::AcqRel);
+                position = self.search(&key, guard);
+                if position.succs[0] != node {
+                    break 'levels;
+                }
+            }
+        }
+
+        if node_ref.next[0].load(Ordering::Acquire, guard).tag() == 1 {
+            // removed while it was being linked, make sure it does not stay on any level
+            self.search(&key, guard);
+        }
+        self.release(node, guard);
+        None
+    }
+
+    pub fn append(&self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let guard = &epoch::pin();
+        let node = self.search(key, guard).found(key)?;
+        // taking the value is what removes the entry, only one thread can do that
+        let value = loop {
+            let value = node.value.load(Ordering::Acquire, guard);
+            if value.is_null() {
+                return None;
+            }
+            if node
+                .value
+                .compare_exchange(
+                    value,
+                    Shared::null(),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                    guard,
+                )
+                .is_ok()
+            {
+                break value;
+            }
+        };
+        self.length.fetch_sub(1, Ordering::SeqCst);
+        node.mark(guard);
+        self.search(key, guard);
+        let removed = unsafe { value.deref() }.clone();
+        unsafe { guard.defer_destroy(value) };
+        Some(removed)
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let guard = &epoch::pin();
+        self.search(key, guard)
+            .found(key)
+            .and_then(|node| node.live_value(guard))
+            .cloned()
+    }
+
+    pub fn find(&self, key: K) -> Option<V> {
+        self.get(&key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        let guard = &epoch::pin();
+        self.search(key, guard)
+            .found(key)
+            .and_then(|node| node.live_value(guard))
+            .is_some()
+    }
+
+    pub fn iter(&self) -> Range<'_, K, V> {
+        self.range(..)
+    }
+
+    // the entries with a key inside `range` in order, as they are while the iterator passes them
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        let guard = epoch::pin();
+        let start = match range.start_bound() {
+            Bound::Included(key) | Bound::Excluded(key) => self.search(key, &guard).succs[0],
+            Bound::Unbounded => self.head[0].load(Ordering::Acquire, &guard),
+        };
+        let mut range_iter = Range {
+            _list: self,
+            current: start.as_raw(),
+            end: range.end_bound().cloned(),
+            guard,
+        };
+        if let Bound::Excluded(key) = range.start_bound() {
+            if unsafe { range_iter.current.as_ref() }.is_some_and(|node| node.key == *key) {
+                range_iter.next();
+            }
+        }
+        range_iter
+    }
+
+    // the entry with the largest key that is less than or equal to `key`
+    pub fn floor(&self, key: &K) -> Option<(K, V)> {
+        self.last_where(|k| k <= key)
+    }
+
+    // the entry with the smallest key that is greater than or equal to `key`
+    pub fn ceiling(&self, key: &K) -> Option<(K, V)> {
+        self.range(key.clone()..).next()
+    }
+
+    pub fn first(&self) -> Option<(K, V)> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<(K, V)> {
+        self.last_where(|_| true)
+    }
+
+    // the entry with the largest key that `accept` holds for, `accept` has to hold for all keys
+    // up to some point and for none after it
+    fn last_where(&self, accept: impl Fn(&K) -> bool) -> Option<(K, V)> {
+        let guard = &epoch::pin();
+        // keys of removed entries the walk ended on, only smaller keys are looked at after that
+        let mut below: Option<K> = None;
+        loop {
+            let accepted = |k: &K| accept(k) && below.as_ref().is_none_or(|b| k < b);
+            // take the express lanes as far as they go, removed nodes can still be walked through
+            let mut links: &[Atomic<Node<K, V>>] = &self.head;
+            let mut candidate = None;
+            for level in (0..=self.max_level).rev() {
+                let mut current = links[level].load(Ordering::Acquire, guard).with_tag(0);
+                while let Some(node) = unsafe { current.as_ref() } {
+                    if !accepted(&node.key) {
+                        break;
+                    }
+                    candidate = Some(node);
+                    links = &node.next;
+                    current = node.next[level].load(Ordering::Acquire, guard).with_tag(0);
+                }
+            }
+            let node = candidate?;
+            match node.live_value(guard) {
+                Some(value) => return Some((node.key.clone(), value.clone())),
+                None => below = Some(node.key.clone()),
+            }
+        }
+    }
+
+    fn get_level(&self) -> usize {
+        let mut rng = SmallRng::seed_from_u64(self.seed.fetch_add(1, Ordering::Relaxed));
+        let mut n = 0;
+        // p(true) = promotion
+        while n < self.max_level && rng.gen_bool(self.promotion) {
+            n += 1;
+        }
+        n
+    }
+
+    // finds the predecessors and successors of `key` on every level and unlinks marked nodes on the way
+    fn search<'g>(&'g self, key: &K, guard: &'g Guard) -> Position<'g, K, V> {
+        'retry: loop {
+            let mut position = Position {
+                preds: vec![&self.head[..]; self.max_level + 1],
+                succs: vec![Shared::null(); self.max_level + 1],
+            };
+            let mut pred: &'g [Atomic<Node<K, V>>] = &self.head;
+            for level in (0..=self.max_level).rev() {
+                let mut current = pred[level].load(Ordering::Acquire, guard);
+                // the predecessor itself got removed
+                if current.tag() == 1 {
+                    continue 'retry;
+                }
+                while let Some(node) = unsafe { current.as_ref() } {
+                    let succ = node.next[level].load(Ordering::Acquire, guard);
+                    if succ.tag() == 1 {
+                        match pred[level].compare_exchange(
+                            current,
+                            succ.with_tag(0),
+                            Ordering::AcqRel,
+                            Ordering::Acquire,
+                            guard,
+                        ) {
+                            Ok(_) => {
+                                self.release(current, guard);
+                                current = succ.with_tag(0);
+                                continue;
+                            }
+                            Err(_) => continue 'retry,
+                        }
+                    }
+                    if node.key < *key {
+                        pred = &node.next;
+                        current = succ;
+                    } else {
+                        break;
+                    }
+                }
+                position.preds[level] = pred;
+                position.succs[level] = current;
+            }
+            return position;
+        }
+    }
+
+    // drops one of the node's links, the last one hands the node over to the garbage collector
+    fn release(&self, node: Shared<'_, Node<K, V>>, guard: &Guard) {
+        if unsafe { node.deref() }
+            .linked
+            .fetch_sub(1, Ordering::AcqRel)
+            == 1
+        {
+            unsafe { guard.defer_destroy(node) };
+        }
+    }
+}
+
+impl<K, V> Drop for ConcurrentSkipList<K, V> {
+    fn drop(&mut self) {
+        // nobody else can use the list anymore, and every removed node has been unlinked already
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut current = self.head[0].load(Ordering::Relaxed, guard);
+            while !current.is_null() {
+                let next = current.deref().next[0].load(Ordering::Relaxed, guard);
+                drop(current.into_owned());
+                current = next.with_tag(0);
+            }
+        }
+    }
+}
+
+pub struct Range<'a, K, V> {
+    _list: &'a ConcurrentSkipList<K, V>,
+    // nodes are not freed while the guard is pinned, so the raw pointer stays valid (this also
+    // holds back reclamation for the whole list, so keep range iterators short lived)
+    current: *const Node<K, V>,
+    end: Bound<K>,
+    guard: Guard,
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for Range<'a, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        loop {
+            let node = unsafe { self.current.as_ref() }?;
+            let next = node.next[0].load(Ordering::Acquire, &self.guard);
+            self.current = next.with_tag(0).as_raw();
+            let in_range = match self.end {
+                Bound::Included(ref end) => node.key <= *end,
+                Bound::Excluded(ref end) => node.key < *end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.current = std::ptr::null();
+                return None;
+            }
+            // skip entries that have been removed
+            if let Some(value) = node.live_value(&self.guard) {
+                return Some((node.key.clone(), value.clone()));
+            }
+        }
+    }
+}
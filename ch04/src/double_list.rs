@@ -1,19 +1,21 @@
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell, RefMut};
+use std::marker::PhantomData;
+use std::mem;
 use std::rc::Rc;
 
 // Rc is a pointer with shared ownership while RefCell provides interior mutability.
 // Because RefCell<T> allows mutable borrows checked at runtime, you can mutate the value inside the RefCell<T> even when the RefCell<T> is immutable.
-type DoubleLink = Option<Rc<RefCell<Node>>>;
+type DoubleLink<T> = Option<Rc<RefCell<Node<T>>>>;
 
 #[derive(Clone)]
-struct Node {
-    value: String,
-    next: DoubleLink,
-    prev: DoubleLink,
+struct Node<T> {
+    value: T,
+    next: DoubleLink<T>,
+    prev: DoubleLink<T>,
 }
 
-impl Node {
-    fn new(value: String) -> Rc<RefCell<Node>> {
+impl<T> Node<T> {
+    fn new(value: T) -> Rc<RefCell<Node<T>>> {
         Rc::new(RefCell::new(Node {
             value: value,
             next: None,
@@ -22,65 +24,393 @@ impl Node {
     }
 }
 
-pub struct ListIterator {
-    current: DoubleLink,
+// Every mutation of the list goes through &mut TransactionLog, so while an iterator holds
+// &'a TransactionLog (or &'a mut for ListIteratorMut) no node can be changed or freed.
+// That is what makes it sound to hand out references that bypass the RefCell.
+fn node_ref<T>(link: &DoubleLink<T>) -> Option<&Node<T>> {
+    link.as_ref().map(|node| unsafe { &*node.as_ptr() })
 }
 
-impl ListIterator {
-    fn new(start_at: DoubleLink) -> ListIterator {
-        ListIterator { current: start_at }
+pub struct ListIterator<'a, T> {
+    front: Option<&'a Node<T>>,
+    back: Option<&'a Node<T>>,
+    remaining: u64,
+}
+
+impl<'a, T> Iterator for ListIterator<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.front.map(|current| {
+            self.front = node_ref(&current.next);
+            self.remaining -= 1;
+            &current.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
     }
 }
 
-impl Iterator for ListIterator {
-    type Item = String;
-    fn next(&mut self) -> Option<String> {
-        let current = &self.current;
-        let mut result = None;
-        self.current = match current {
-            Some(ref current) => {
-                let current = current.borrow();
-                result = Some(current.value.clone());
-                current.next.clone()
+impl<'a, T> DoubleEndedIterator for ListIterator<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.back.map(|current| {
+            self.back = node_ref(&current.prev);
+            self.remaining -= 1;
+            &current.value
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ListIterator<'a, T> {}
+
+pub struct ListIteratorMut<'a, T> {
+    front: DoubleLink<T>,
+    back: DoubleLink<T>,
+    // index of the front node and one past the back node
+    front_index: u64,
+    back_index: u64,
+    remaining: u64,
+    // with history on, every value is recorded before it is handed out, so changes can be undone
+    history: Option<&'a mut History<T>>,
+    _list: PhantomData<&'a mut TransactionLog<T>>,
+}
+
+impl<'a, T> Iterator for ListIteratorMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.front.take().map(|current| {
+            // front and back never hand out the same node because of `remaining`
+            let node = unsafe { &mut *current.as_ptr() };
+            self.front = node.next.clone();
+            self.remaining -= 1;
+            if let Some(ref mut history) = self.history {
+                let old = (history.clone_value)(&node.value);
+                history.record(Undo::Edit(self.front_index, old));
             }
-            None => None,
-        };
-        result
+            self.front_index += 1;
+            &mut node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
     }
 }
 
-impl DoubleEndedIterator for ListIterator {
-    fn next_back(&mut self) -> Option<String> {
-        let current = &self.current;
-        let mut result = None;
-        self.current = match current {
-            Some(ref current) => {
-                let current = current.borrow();
-                result = Some(current.value.clone());
-                current.prev.clone()
+impl<'a, T> DoubleEndedIterator for ListIteratorMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.back.take().map(|current| {
+            let node = unsafe { &mut *current.as_ptr() };
+            self.back = node.prev.clone();
+            self.remaining -= 1;
+            self.back_index -= 1;
+            if let Some(ref mut history) = self.history {
+                let old = (history.clone_value)(&node.value);
+                history.record(Undo::Edit(self.back_index, old));
             }
-            None => None,
-        };
-        result
+            &mut node.value
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ListIteratorMut<'a, T> {}
+
+pub struct IntoIter<T> {
+    list: TransactionLog<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
     }
 }
 
-struct TransactionLog {
-    head: DoubleLink,
-    tail: DoubleLink,
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+}
+
+// Undo and redo entries only keep what they need to revert a change: undoing an append removes the
+// tail again, while undoing a pop needs the popped value to put it back in front. Changes in the
+// middle of the list, made through a cursor or iter_mut, keep the index they happened at.
+enum Undo<T> {
+    Append,
+    Pop(T),
+    Insert(u64),
+    Remove(u64, T),
+    // the value before it was handed out to be changed
+    Edit(u64, T),
+}
+
+enum Redo<T> {
+    Append(T),
+    Pop,
+    Insert(u64, T),
+    Remove(u64),
+    Edit(u64, T),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    id: u64,
+}
+
+struct History<T> {
+    undo: Vec<Undo<T>>,
+    redo: Vec<Redo<T>>,
+    // open checkpoints with the size of the undo stack when they were taken, outermost first
+    checkpoints: Vec<(Checkpoint, usize)>,
+    next_checkpoint: u64,
+    // `pop`, `remove_current` and edits hand the value to the caller, so a copy for the undo stack
+    // is needed. This is captured when the history is switched on, which keeps the Clone bound off
+    // the plain list methods.
+    clone_value: fn(&T) -> T,
+    // false if it was only switched on by a checkpoint, it is switched off again with the last one
+    keep: bool,
+}
+
+impl<T> History<T> {
+    // a new change means whatever was undone can't be redone anymore
+    fn record(&mut self, change: Undo<T>) {
+        self.undo.push(change);
+        self.redo.clear();
+    }
+}
+
+pub struct TransactionLog<T> {
+    head: DoubleLink<T>,
+    tail: DoubleLink<T>,
+    history: Option<History<T>>,
     pub length: u64,
 }
 
-impl TransactionLog {
-    pub fn new_empty() -> TransactionLog {
+impl<T> TransactionLog<T> {
+    pub fn new_empty() -> TransactionLog<T> {
         TransactionLog {
             head: None,
             tail: None,
+            history: None,
             length: 0,
         }
     }
 
-    pub fn append(&mut self, value: String) {
+    pub fn append(&mut self, value: T) {
+        self.push_back(value);
+        self.record(|_| Undo::Append);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let value = self.pop_front()?;
+        self.record(|clone| Undo::Pop(clone(&value)));
+        Some(value)
+    }
+
+    // reverts the most recent append or pop
+    pub fn undo(&mut self) -> bool {
+        let change = match self.history {
+            Some(ref mut history) => match history.undo.pop() {
+                Some(change) => change,
+                None => return false,
+            },
+            None => return false,
+        };
+        let redo = match change {
+            Undo::Append => Redo::Append(self.pop_back().expect("undo an append on an empty list")),
+            Undo::Pop(value) => {
+                self.prepend(value);
+                Redo::Pop
+            }
+            Undo::Insert(index) => Redo::Insert(index, self.remove_at(index)),
+            Undo::Remove(index, value) => {
+                self.insert_at(index, value);
+                Redo::Remove(index)
+            }
+            Undo::Edit(index, value) => Redo::Edit(index, self.replace_at(index, value)),
+        };
+        if let Some(ref mut history) = self.history {
+            history.redo.push(redo);
+            // checkpoints taken after the change that was undone are gone
+            let depth = history.undo.len();
+            history.checkpoints.retain(|(_, at)| *at <= depth);
+        }
+        true
+    }
+
+    // replays the most recently undone change
+    pub fn redo(&mut self) -> bool {
+        let change = match self.history {
+            Some(ref mut history) => match history.redo.pop() {
+                Some(change) => change,
+                None => return false,
+            },
+            None => return false,
+        };
+        let undo = match change {
+            Redo::Append(value) => {
+                self.push_back(value);
+                Undo::Append
+            }
+            Redo::Pop => Undo::Pop(self.pop_front().expect("redo a pop on an empty list")),
+            Redo::Insert(index, value) => {
+                self.insert_at(index, value);
+                Undo::Insert(index)
+            }
+            Redo::Remove(index) => Undo::Remove(index, self.remove_at(index)),
+            Redo::Edit(index, value) => Undo::Edit(index, self.replace_at(index, value)),
+        };
+        if let Some(ref mut history) = self.history {
+            history.undo.push(undo);
+        }
+        true
+    }
+
+    // undoes everything since the checkpoint was taken, the checkpoint itself stays usable
+    // while checkpoints nested inside it are released
+    pub fn rollback_to(&mut self, checkpoint: Checkpoint) -> bool {
+        let depth = match self.history {
+            Some(ref mut history) => match history
+                .checkpoints
+                .iter()
+                .position(|(c, _)| *c == checkpoint)
+            {
+                Some(i) => {
+                    history.checkpoints.truncate(i + 1);
+                    history.checkpoints[i].1
+                }
+                None => return false,
+            },
+            None => return false,
+        };
+        while self.history.as_ref().map_or(0, |h| h.undo.len()) > depth {
+            self.undo();
+        }
+        if let Some(ref mut history) = self.history {
+            history.redo.clear();
+        }
+        true
+    }
+
+    // keeps all changes since the checkpoint, and drops it together with any nested ones
+    pub fn release(&mut self, checkpoint: Checkpoint) -> bool {
+        match self.history {
+            Some(ref mut history) => match history
+                .checkpoints
+                .iter()
+                .position(|(c, _)| *c == checkpoint)
+            {
+                Some(i) => {
+                    history.checkpoints.truncate(i);
+                    self.drop_unused_history();
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    // clears the undo and redo stacks, open checkpoints can't be rolled back anymore
+    pub fn forget_history(&mut self) {
+        if let Some(ref mut history) = self.history {
+            history.undo.clear();
+            history.redo.clear();
+            history.checkpoints.clear();
+        }
+        self.drop_unused_history();
+    }
+
+    // keeps a change on the undo stack if history is on, `change` gets the function to copy values
+    fn record(&mut self, change: impl FnOnce(fn(&T) -> T) -> Undo<T>) {
+        if let Some(ref mut history) = self.history {
+            history.record(change(history.clone_value));
+        }
+    }
+
+    // a split moves nodes into another list, which the undo stack can't follow
+    fn forget_history_for_split(&mut self) {
+        assert!(
+            self.history
+                .as_ref()
+                .is_none_or(|h| h.checkpoints.is_empty()),
+            "a split can't be rolled back, release the open checkpoints first"
+        );
+        self.forget_history();
+    }
+
+    // a cursor on `index`, walking in from the nearer end
+    fn cursor_at(&mut self, index: u64) -> CursorMut<'_, T> {
+        assert!(
+            index < self.length,
+            "undo or redo at index {} out of bounds",
+            index
+        );
+        if index < self.length / 2 {
+            let mut cursor = self.cursor_front_mut();
+            for _ in 0..index {
+                cursor.move_next();
+            }
+            cursor
+        } else {
+            let steps = self.length - 1 - index;
+            let mut cursor = self.cursor_back_mut();
+            for _ in 0..steps {
+                cursor.move_prev();
+            }
+            cursor
+        }
+    }
+
+    fn insert_at(&mut self, index: u64, value: T) {
+        if index == self.length {
+            self.push_back(value);
+        } else {
+            self.cursor_at(index).link_before(value);
+        }
+    }
+
+    fn remove_at(&mut self, index: u64) -> T {
+        self.cursor_at(index)
+            .unlink_current()
+            .expect("the cursor is on a node")
+    }
+
+    fn replace_at(&mut self, index: u64, value: T) -> T {
+        let node = self
+            .cursor_at(index)
+            .current
+            .clone()
+            .expect("the cursor is on a node");
+        let mut node = node.borrow_mut();
+        mem::replace(&mut node.value, value)
+    }
+
+    // without an open checkpoint nobody can roll back, so the stacks would only grow
+    fn drop_unused_history(&mut self) {
+        if self
+            .history
+            .as_ref()
+            .is_some_and(|h| !h.keep && h.checkpoints.is_empty())
+        {
+            self.history = None;
+        }
+    }
+
+    fn push_back(&mut self, value: T) {
         let new = Node::new(value);
         // https://doc.rust-lang.org/std/option/enum.Option.html#method.take
         match self.tail.take() {
@@ -94,7 +424,7 @@ impl TransactionLog {
         self.tail = Some(new);
     }
 
-    pub fn pop(&mut self) -> Option<String> {
+    fn pop_front(&mut self) -> Option<T> {
         self.head.take().map(|head| {
             if let Some(next) = head.borrow_mut().next.take() {
                 next.borrow_mut().prev = None;
@@ -111,21 +441,361 @@ impl TransactionLog {
         })
     }
 
-    pub fn back_iter(self) -> ListIterator {
-        ListIterator::new(self.tail)
+    // the iterator is double ended, so walking backwards from the tail is `back_iter().rev()`
+    pub fn back_iter(&self) -> ListIterator<'_, T> {
+        self.iter()
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        ListIterator {
+            front: node_ref(&self.head),
+            back: node_ref(&self.tail),
+            remaining: self.length,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> ListIteratorMut<'_, T> {
+        ListIteratorMut {
+            front: self.head.clone(),
+            back: self.tail.clone(),
+            front_index: 0,
+            back_index: self.length,
+            remaining: self.length,
+            history: self.history.as_mut(),
+            _list: PhantomData,
+        }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        let index = self.head.as_ref().map(|_| 0);
+        CursorMut {
+            current: self.head.clone(),
+            index,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        let index = self.tail.as_ref().map(|_| self.length - 1);
+        CursorMut {
+            current: self.tail.clone(),
+            index,
+            list: self,
+        }
+    }
+
+    fn pop_back(&mut self) -> Option<T> {
+        self.tail.take().map(|tail| {
+            if let Some(prev) = tail.borrow_mut().prev.take() {
+                prev.borrow_mut().next = None;
+                self.tail = Some(prev);
+            } else {
+                self.head.take();
+            }
+            self.length -= 1;
+            Rc::try_unwrap(tail)
+                .ok()
+                .expect("sth is wrong")
+                .into_inner()
+                .value
+        })
+    }
+
+    fn prepend(&mut self, value: T) {
+        let new = Node::new(value);
+        match self.head.take() {
+            Some(old) => {
+                old.borrow_mut().prev = Some(new.clone());
+                new.borrow_mut().next = Some(old);
+            }
+            None => self.tail = Some(new.clone()),
+        };
+        self.length += 1;
+        self.head = Some(new);
+    }
+}
+
+impl<T: Clone> TransactionLog<T> {
+    // a log that records appends and pops from the start, so they can be undone
+    pub fn new_with_history() -> TransactionLog<T> {
+        let mut log = TransactionLog::new_empty();
+        log.enable_history();
+        if let Some(ref mut history) = log.history {
+            history.keep = true;
+        }
+        log
+    }
+
+    // checkpoints can be nested, rolling back to an outer one also drops the inner ones
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.enable_history();
+        let history = self.history.as_mut().expect("history is enabled");
+        let checkpoint = Checkpoint {
+            id: history.next_checkpoint,
+        };
+        history.next_checkpoint += 1;
+        history.checkpoints.push((checkpoint, history.undo.len()));
+        checkpoint
     }
 
-    pub fn iter(&self) -> ListIterator {
-        ListIterator::new(self.head.clone())
+    fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(History {
+                undo: vec![],
+                redo: vec![],
+                checkpoints: vec![],
+                next_checkpoint: 0,
+                clone_value: T::clone,
+                keep: false,
+            });
+        }
     }
 }
 
-impl IntoIterator for TransactionLog {
-    type Item = String;
-    type IntoIter = ListIterator;
+impl<T> IntoIterator for TransactionLog<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        ListIterator::new(self.head)
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a TransactionLog<T> {
+    type Item = &'a T;
+    type IntoIter = ListIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut TransactionLog<T> {
+    type Item = &'a mut T;
+    type IntoIter = ListIteratorMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// A cursor points at a node of the list, or at the "ghost" position between tail and head
+// (current == None). Moving past either end lands on the ghost, moving again wraps around.
+// Every operation only relinks the neighbours of the current node, so all of them are O(1).
+pub struct CursorMut<'a, T> {
+    current: DoubleLink<T>,
+    index: Option<u64>,
+    list: &'a mut TransactionLog<T>,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<u64> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            Some(current) => {
+                self.current = current.borrow().next.clone();
+                self.index = match self.current {
+                    Some(_) => self.index.map(|i| i + 1),
+                    None => None,
+                };
+            }
+            None => {
+                self.current = self.list.head.clone();
+                self.index = self.current.as_ref().map(|_| 0);
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.current.take() {
+            Some(current) => {
+                self.current = current.borrow().prev.clone();
+                self.index = match self.current {
+                    Some(_) => self.index.map(|i| i - 1),
+                    None => None,
+                };
+            }
+            None => {
+                self.current = self.list.tail.clone();
+                self.index = self.current.as_ref().map(|_| self.list.length - 1);
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<Ref<'_, T>> {
+        self.current
+            .as_ref()
+            .map(|current| Ref::map(current.borrow(), |node| &node.value))
+    }
+
+    // with history on, the old value goes on the undo stack first
+    pub fn current_mut(&mut self) -> Option<RefMut<'_, T>> {
+        let current = self.current.as_ref()?;
+        if let Some(index) = self.index {
+            self.list
+                .record(|clone| Undo::Edit(index, clone(&current.borrow().value)));
+        }
+        Some(RefMut::map(current.borrow_mut(), |node| &mut node.value))
+    }
+
+    // the cursor holds the only &mut to the list, so while it is borrowed here the neighbour
+    // can't be unlinked and node_ref is sound
+    pub fn peek_next(&self) -> Option<Ref<'_, T>> {
+        let next = match node_ref(&self.current) {
+            Some(current) => current.next.as_ref(),
+            None => self.list.head.as_ref(),
+        };
+        next.map(|next| Ref::map(next.borrow(), |node| &node.value))
+    }
+
+    pub fn peek_prev(&self) -> Option<Ref<'_, T>> {
+        let prev = match node_ref(&self.current) {
+            Some(current) => current.prev.as_ref(),
+            None => self.list.tail.as_ref(),
+        };
+        prev.map(|prev| Ref::map(prev.borrow(), |node| &node.value))
+    }
+
+    pub fn insert_after(&mut self, value: T) {
+        // after the ghost is the head of the list
+        let index = self.index.map_or(0, |i| i + 1);
+        self.list.record(|_| Undo::Insert(index));
+        self.link_after(value);
+    }
+
+    pub fn insert_before(&mut self, value: T) {
+        // before the ghost is the tail of the list
+        let index = self.index.unwrap_or(self.list.length);
+        self.list.record(|_| Undo::Insert(index));
+        self.link_before(value);
+    }
+
+    // removes the current node and moves the cursor to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        let index = self.index?;
+        let value = self.unlink_current()?;
+        self.list.record(|clone| Undo::Remove(index, clone(&value)));
+        Some(value)
+    }
+
+    fn link_after(&mut self, value: T) {
+        match self.current {
+            Some(ref current) => {
+                let new = Node::new(value);
+                let next = current.borrow_mut().next.take();
+                match next {
+                    Some(ref next) => next.borrow_mut().prev = Some(new.clone()),
+                    None => self.list.tail = Some(new.clone()),
+                };
+                new.borrow_mut().next = next;
+                new.borrow_mut().prev = Some(current.clone());
+                current.borrow_mut().next = Some(new);
+                self.list.length += 1;
+            }
+            // after the ghost is the head of the list
+            None => self.list.prepend(value),
+        }
+    }
+
+    fn link_before(&mut self, value: T) {
+        match self.current {
+            Some(ref current) => {
+                let new = Node::new(value);
+                let prev = current.borrow_mut().prev.take();
+                match prev {
+                    Some(ref prev) => prev.borrow_mut().next = Some(new.clone()),
+                    None => self.list.head = Some(new.clone()),
+                };
+                new.borrow_mut().prev = prev;
+                new.borrow_mut().next = Some(current.clone());
+                current.borrow_mut().prev = Some(new);
+                self.list.length += 1;
+                self.index = self.index.map(|i| i + 1);
+            }
+            // before the ghost is the tail of the list
+            None => self.list.push_back(value),
+        }
+    }
+
+    fn unlink_current(&mut self) -> Option<T> {
+        let current = self.current.take()?;
+        let prev = current.borrow_mut().prev.take();
+        let next = current.borrow_mut().next.take();
+        match prev {
+            Some(ref prev) => prev.borrow_mut().next = next.clone(),
+            None => self.list.head = next.clone(),
+        };
+        match next {
+            Some(ref next) => next.borrow_mut().prev = prev,
+            None => self.list.tail = prev,
+        };
+        if next.is_none() {
+            self.index = None;
+        }
+        self.current = next;
+        self.list.length -= 1;
+        Some(
+            Rc::try_unwrap(current)
+                .ok()
+                .expect("sth is wrong")
+                .into_inner()
+                .value,
+        )
+    }
+
+    // everything after the cursor is moved into a new list. This can't be undone, so it panics while
+    // a checkpoint is open and clears the undo and redo stacks otherwise.
+    pub fn split_after(&mut self) -> TransactionLog<T> {
+        self.list.forget_history_for_split();
+        match self.current {
+            Some(ref current) => {
+                let index = self.index.unwrap_or(0);
+                match current.borrow_mut().next.take() {
+                    Some(next) => {
+                        next.borrow_mut().prev = None;
+                        let rest = TransactionLog {
+                            head: Some(next),
+                            tail: self.list.tail.replace(current.clone()),
+                            history: None,
+                            length: self.list.length - index - 1,
+                        };
+                        self.list.length = index + 1;
+                        rest
+                    }
+                    None => TransactionLog::new_empty(),
+                }
+            }
+            None => mem::replace(self.list, TransactionLog::new_empty()),
+        }
+    }
+
+    // everything before the cursor is moved into a new list, like split_after it can't be undone
+    pub fn split_before(&mut self) -> TransactionLog<T> {
+        self.list.forget_history_for_split();
+        match self.current {
+            Some(ref current) => {
+                let index = self.index.unwrap_or(0);
+                self.index = Some(0);
+                match current.borrow_mut().prev.take() {
+                    Some(prev) => {
+                        prev.borrow_mut().next = None;
+                        let rest = TransactionLog {
+                            head: self.list.head.replace(current.clone()),
+                            tail: Some(prev),
+                            history: None,
+                            length: index,
+                        };
+                        self.list.length -= index;
+                        rest
+                    }
+                    None => TransactionLog::new_empty(),
+                }
+            }
+            None => mem::replace(self.list, TransactionLog::new_empty()),
+        }
     }
 }
 
@@ -0,0 +1,435 @@
+use crate::double_list::{ListIterator, TransactionLog};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// On disk the log is a directory of segment files named by a running id ("00000000000000000003.log").
+// Every append and every pop is written as one record to the newest (active) segment:
+//
+//   | body length: u32 | adler32 of body: u32 | kind: u8 | offset: u64 | payload |
+//
+// All integers are little endian. Each appended value gets an increasing offset, a pop record stores
+// the offset it removed, so replaying the segments rebuilds exactly the list that was in memory.
+// A crash in the middle of a write leaves a short or corrupt record at the end of the last segment,
+// which is cut off again when the log is reopened. A bad record anywhere else, or one that passes
+// the checksum but does not decode, is reported as InvalidData and the segment is left alone.
+
+const SEGMENT_EXTENSION: &str = "log";
+const HEADER_SIZE: usize = 8;
+const KIND_APPEND: u8 = 0;
+const KIND_POP: u8 = 1;
+const MOD_ADLER: u32 = 65521;
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+
+    for byte in bytes {
+        a = (a + *byte as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+// values have to be turned into bytes and back to be written to a segment
+pub trait LogRecord: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl LogRecord for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<String> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl LogRecord for u64 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<u64> {
+        let mut buf = [0; 8];
+        if bytes.len() != buf.len() {
+            return None;
+        }
+        buf.copy_from_slice(bytes);
+        Some(u64::from_le_bytes(buf))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Retention {
+    Unlimited,
+    // keep at most this many segments, including the active one
+    Segments(usize),
+    // drop closed segments that have not been written to for this long
+    Age(Duration),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LogConfig {
+    pub max_segment_bytes: u64,
+    pub retention: Retention,
+    // fsync after every record instead of leaving it to the OS (or to `sync`)
+    pub sync_on_write: bool,
+}
+
+impl LogConfig {
+    pub fn new() -> LogConfig {
+        LogConfig {
+            max_segment_bytes: 1 << 20,
+            retention: Retention::Unlimited,
+            sync_on_write: false,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig::new()
+    }
+}
+
+enum Entry<T> {
+    Append(u64, T),
+    Pop(u64),
+}
+
+// why a record could not be read
+enum BadRecord {
+    // the header or the body runs past the end of the file
+    Short,
+    // length, checksum or kind don't add up, the record claims to be this many bytes long
+    Corrupt(usize),
+    // the checksum matches, but the payload is not a T
+    Undecodable,
+}
+
+struct Segment {
+    id: u64,
+    // offset of the first value appended to this segment, everything before lives in older ones
+    base_offset: u64,
+}
+
+pub struct DurableTransactionLog<T> {
+    dir: PathBuf,
+    config: LogConfig,
+    // all segments on disk, oldest first, the last one is the active segment
+    segments: Vec<Segment>,
+    active: File,
+    active_size: u64,
+    log: TransactionLog<T>,
+    // offset of the head of `log`, and the offset the next append will get
+    first_offset: u64,
+    next_offset: u64,
+    // set when a failed write could not be taken back out of the active segment
+    failed: bool,
+}
+
+impl<T: LogRecord> DurableTransactionLog<T> {
+    pub fn open(dir: impl AsRef<Path>, config: LogConfig) -> io::Result<DurableTransactionLog<T>> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut ids = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(id) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+
+        let mut log = TransactionLog::new_empty();
+        let mut first_offset = 0;
+        let mut next_offset = 0;
+        let mut segments = vec![];
+        for (i, id) in ids.iter().enumerate() {
+            let path = segment_path(&dir, *id);
+            let is_last = i + 1 == ids.len();
+            let entries = read_segment(&path, is_last)?;
+            let base_offset = match entries.first() {
+                Some(Entry::Append(offset, _)) => *offset,
+                _ => next_offset,
+            };
+            segments.push(Segment {
+                id: *id,
+                base_offset,
+            });
+            for entry in entries {
+                match entry {
+                    Entry::Append(offset, value) => {
+                        // older segments may have been dropped by retention
+                        if log.length == 0 {
+                            first_offset = offset;
+                        }
+                        log.append(value);
+                        next_offset = offset + 1;
+                    }
+                    Entry::Pop(offset) => {
+                        while log.length > 0 && first_offset <= offset {
+                            log.pop();
+                            first_offset += 1;
+                        }
+                    }
+                }
+            }
+        }
+        if log.length == 0 {
+            first_offset = next_offset;
+        }
+
+        if segments.is_empty() {
+            segments.push(Segment {
+                id: 0,
+                base_offset: next_offset,
+            });
+        }
+        let active = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(&dir, segments[segments.len() - 1].id))?;
+        let active_size = active.metadata()?.len();
+
+        let mut durable = DurableTransactionLog {
+            dir,
+            config,
+            segments,
+            active,
+            active_size,
+            log,
+            first_offset,
+            next_offset,
+            failed: false,
+        };
+        durable.apply_retention()?;
+        Ok(durable)
+    }
+
+    pub fn append(&mut self, value: T) -> io::Result<()> {
+        self.check_failed()?;
+        self.rotate_if_full()?;
+        let offset = self.next_offset;
+        self.write_record(KIND_APPEND, offset, &value.encode())?;
+        self.log.append(value);
+        self.next_offset += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> io::Result<Option<T>> {
+        self.check_failed()?;
+        // rotating may apply retention, which can drop the head
+        self.rotate_if_full()?;
+        if self.log.length == 0 {
+            return Ok(None);
+        }
+        let offset = self.first_offset;
+        self.write_record(KIND_POP, offset, &[])?;
+        self.first_offset += 1;
+        Ok(self.log.pop())
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.active.sync_data()
+    }
+
+    pub fn len(&self) -> u64 {
+        self.log.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.length == 0
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        self.log.iter()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn write_record(&mut self, kind: u8, offset: u64, payload: &[u8]) -> io::Result<()> {
+        let mut body = Vec::with_capacity(9 + payload.len());
+        body.push(kind);
+        body.extend_from_slice(&offset.to_le_bytes());
+        body.extend_from_slice(payload);
+
+        let mut record = Vec::with_capacity(HEADER_SIZE + body.len());
+        record.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        record.extend_from_slice(&adler32(&body).to_le_bytes());
+        record.extend_from_slice(&body);
+
+        // a single write, so a crash can only tear the last record
+        let written = self.active.write_all(&record).and_then(|_| {
+            if self.config.sync_on_write {
+                self.active.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = written {
+            // cut off whatever part of the record made it, otherwise the next records would be
+            // written after it and thrown away with it on the next open
+            if self.active.set_len(self.active_size).is_err() {
+                self.failed = true;
+            }
+            return Err(e);
+        }
+        self.active_size += record.len() as u64;
+        Ok(())
+    }
+
+    fn check_failed(&self) -> io::Result<()> {
+        if self.failed {
+            return Err(io::Error::other(
+                "a failed write left a partial record in the log, it has to be reopened",
+            ));
+        }
+        Ok(())
+    }
+
+    fn rotate_if_full(&mut self) -> io::Result<()> {
+        if self.active_size < self.config.max_segment_bytes {
+            return Ok(());
+        }
+        self.active.sync_data()?;
+        let id = self.segments[self.segments.len() - 1].id + 1;
+        self.active = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(&self.dir, id))?;
+        self.active_size = 0;
+        self.segments.push(Segment {
+            id,
+            base_offset: self.next_offset,
+        });
+        self.apply_retention()
+    }
+
+    fn apply_retention(&mut self) -> io::Result<()> {
+        let expired = match self.config.retention {
+            Retention::Unlimited => 0,
+            Retention::Segments(max) => self.segments.len().saturating_sub(max.max(1)),
+            Retention::Age(max_age) => {
+                let now = SystemTime::now();
+                let mut expired = 0;
+                // the active segment is never dropped
+                for segment in &self.segments[..self.segments.len() - 1] {
+                    let modified = fs::metadata(segment_path(&self.dir, segment.id))?.modified()?;
+                    match now.duration_since(modified) {
+                        Ok(age) if age > max_age => expired += 1,
+                        _ => break,
+                    }
+                }
+                expired
+            }
+        };
+        for segment in self.segments.drain(..expired) {
+            fs::remove_file(segment_path(&self.dir, segment.id))?;
+        }
+        // values whose append record is gone would not survive a restart, so drop them now
+        let base_offset = self.segments[0].base_offset;
+        while self.log.length > 0 && self.first_offset < base_offset {
+            self.log.pop();
+            self.first_offset += 1;
+        }
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", id, SEGMENT_EXTENSION))
+}
+
+// reads all records of a segment, a torn record at the end of the last segment is truncated
+fn read_segment<T: LogRecord>(path: &Path, is_last: bool) -> io::Result<Vec<Entry<T>>> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let mut entries = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let end_of_file = bytes.len() - pos;
+        match parse_record(&bytes[pos..]) {
+            Ok((entry, size)) => {
+                entries.push(entry);
+                pos += size;
+            }
+            // only the write that was going on when the process died can be torn
+            Err(BadRecord::Short) if is_last => {
+                truncate(path, pos)?;
+                break;
+            }
+            Err(BadRecord::Corrupt(size)) if is_last && size == end_of_file => {
+                truncate(path, pos)?;
+                break;
+            }
+            Err(BadRecord::Undecodable) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "record in {} at byte {} does not decode as this log's values",
+                        path.display(),
+                        pos
+                    ),
+                ))
+            }
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("corrupt record in {} at byte {}", path.display(), pos),
+                ))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn truncate(path: &Path, len: usize) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(len as u64)
+}
+
+fn parse_record<T: LogRecord>(bytes: &[u8]) -> Result<(Entry<T>, usize), BadRecord> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(BadRecord::Short);
+    }
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[0..4]);
+    let length = u32::from_le_bytes(word) as usize;
+    word.copy_from_slice(&bytes[4..8]);
+    let checksum = u32::from_le_bytes(word);
+
+    let body = bytes
+        .get(HEADER_SIZE..HEADER_SIZE + length)
+        .ok_or(BadRecord::Short)?;
+    if length < 9 || adler32(body) != checksum {
+        return Err(BadRecord::Corrupt(HEADER_SIZE + length));
+    }
+    let mut offset = [0; 8];
+    offset.copy_from_slice(&body[1..9]);
+    let offset = u64::from_le_bytes(offset);
+    let entry = match body[0] {
+        KIND_APPEND => Entry::Append(offset, T::decode(&body[9..]).ok_or(BadRecord::Undecodable)?),
+        KIND_POP => Entry::Pop(offset),
+        _ => return Err(BadRecord::Corrupt(HEADER_SIZE + length)),
+    };
+    Ok((entry, HEADER_SIZE + length))
+}
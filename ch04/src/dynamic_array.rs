@@ -1,110 +1,540 @@
-use std::boxed::Box; 
-use std::cmp; 
-use std::cell::Cell;
-use std::f32::MIN; 
+use std::alloc::{self, Layout};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::ptr::{self, NonNull};
+use std::slice::{self, SliceIndex};
 
-const MIN_SIZE: usize = 10; 
-type Node = Option<u64>; 
+// ref
+// The Rustonomicon, "Example: Implementing Vec"
+// https://doc.rust-lang.org/nomicon/vec/vec.html
+//
+// The buffer is allocated uninitialized and only the first `length` slots hold values, so nothing
+// has to be stored for the free capacity. Growing reallocates in place where the allocator can,
+// values are moved bit by bit otherwise, never cloned. Zero sized types never allocate.
+// How much to grow by is up to a GrowthPolicy, `stats` shows what that policy has cost so far.
 
-pub struct TimestampSaver {
-    buf: Box<[Node]>, 
-    cap: usize, 
-    pub length: usize, 
+const MIN_SIZE: usize = 10;
+
+// picks the new capacity when `min_cap` values don't fit into `cap` anymore
+pub trait GrowthPolicy: Send + Sync {
+    fn grow(&self, cap: usize, min_cap: usize) -> usize;
 }
 
-impl TimestampSaver {
-    pub fn new_empty() -> TimestampSaver {
-        TimestampSaver { 
-            buf: Box::new([None; MIN_SIZE]), 
-            cap: MIN_SIZE, 
-            length: 0
-        }
+// cap * 2, starting at MIN_SIZE
+pub struct Doubling;
+
+// cap * 1.5, starting at MIN_SIZE
+pub struct OneAndAHalf;
+
+// cap + a fixed number of values
+pub struct FixedChunk(pub usize);
+
+// exactly min_cap, i.e. a reallocation for every push
+pub struct Exact;
+
+impl GrowthPolicy for Doubling {
+    fn grow(&self, cap: usize, min_cap: usize) -> usize {
+        cap.saturating_mul(2).max(min_cap).max(MIN_SIZE)
     }
+}
 
-    fn grow(&mut self, min_cap: usize) {
-        let old_cap = self.buf.len(); 
-        let mut new_cap = old_cap + (old_cap >> 1); 
+impl GrowthPolicy for OneAndAHalf {
+    fn grow(&self, cap: usize, min_cap: usize) -> usize {
+        cap.saturating_add(cap >> 1).max(min_cap).max(MIN_SIZE)
+    }
+}
 
-        new_cap = cmp::max(new_cap, min_cap); 
-        new_cap = cmp::min(new_cap, usize::max_value()); 
-        let current = self.buf.clone(); 
-        self.cap = new_cap; 
+impl GrowthPolicy for FixedChunk {
+    fn grow(&self, cap: usize, min_cap: usize) -> usize {
+        assert!(self.0 > 0, "chunk size has to be at least 1");
+        // as many chunks as it takes
+        let missing = min_cap.saturating_sub(cap);
+        cap.saturating_add(missing.div_ceil(self.0).max(1).saturating_mul(self.0))
+    }
+}
 
-        self.buf = vec![None; new_cap].into_boxed_slice(); 
-        self.buf[..current.len()].clone_from_slice(&current); 
+impl GrowthPolicy for Exact {
+    fn grow(&self, _cap: usize, min_cap: usize) -> usize {
+        min_cap
     }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AllocationStats {
+    // times the buffer was allocated, resized or freed by shrink_to_fit
+    pub reallocations: u64,
+    // bytes moved because the allocator could not resize in place
+    pub bytes_copied: u64,
+    // bytes allocated but not holding a value right now
+    pub wasted_bytes: u64,
+}
 
-    pub fn at(&mut self, index: usize) -> Option<u64> {
-        if self.length > index {
-            self.buf[index]
+pub struct DynamicArray<T> {
+    buf: NonNull<T>,
+    cap: usize,
+    length: usize,
+    policy: Box<dyn GrowthPolicy>,
+    // wasted_bytes is worked out when the stats are asked for
+    stats: AllocationStats,
+    // owns values of T, for the drop check
+    _marker: PhantomData<T>,
+}
+
+// the timestamp log is an array of u64
+pub type TimestampSaver = DynamicArray<u64>;
+
+unsafe impl<T: Send> Send for DynamicArray<T> {}
+unsafe impl<T: Sync> Sync for DynamicArray<T> {}
+
+impl<T> DynamicArray<T> {
+    pub fn new_empty() -> DynamicArray<T> {
+        DynamicArray::with_policy(OneAndAHalf)
+    }
+
+    pub fn with_policy(policy: impl GrowthPolicy + 'static) -> DynamicArray<T> {
+        DynamicArray {
+            buf: NonNull::dangling(),
+            cap: if Self::is_zst() { usize::MAX } else { 0 },
+            length: 0,
+            policy: Box::new(policy),
+            stats: AllocationStats::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_capacity(cap: usize) -> DynamicArray<T> {
+        let mut arr = DynamicArray::new_empty();
+        arr.reserve(cap);
+        arr
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn stats(&self) -> AllocationStats {
+        let wasted = if Self::is_zst() {
+            0
         } else {
-            None 
+            self.cap - self.length
+        };
+        AllocationStats {
+            wasted_bytes: (wasted * std::mem::size_of::<T>()) as u64,
+            ..self.stats
         }
     }
 
-    pub fn append(&mut self, value: u64) {
-        if self.length == self.cap { 
+    pub fn push(&mut self, value: T) {
+        if self.length == self.cap {
             self.grow(self.length + 1);
         }
-        self.buf[self.length] = Some(value); 
-        self.length += 1; 
+        unsafe { ptr::write(self.buf.as_ptr().add(self.length), value) };
+        self.length += 1;
     }
 
-    pub fn back_iter(self) -> ArrayIterator {
-        ArrayIterator::new(self.length-1, self.buf.clone())
+    pub fn append(&mut self, value: T) {
+        self.push(value);
     }
 
-    pub fn iter(&self) -> ArrayIterator {
-        ArrayIterator::new(0, self.buf.clone())
+    pub fn pop(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        Some(unsafe { ptr::read(self.buf.as_ptr().add(self.length)) })
+    }
+
+    // shifts everything from `index` on to the right, panics if `index > len`
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(
+            index <= self.length,
+            "insertion index {} is out of bounds for length {}",
+            index,
+            self.length
+        );
+        if self.length == self.cap {
+            self.grow(self.length + 1);
+        }
+        unsafe {
+            let at = self.buf.as_ptr().add(index);
+            ptr::copy(at, at.add(1), self.length - index);
+            ptr::write(at, value);
+        }
+        self.length += 1;
+    }
+
+    // shifts everything after `index` to the left, panics if `index >= len`
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.length,
+            "removal index {} is out of bounds for length {}",
+            index,
+            self.length
+        );
+        self.length -= 1;
+        unsafe {
+            let at = self.buf.as_ptr().add(index);
+            let value = ptr::read(at);
+            ptr::copy(at.add(1), at, self.length - index);
+            value
+        }
+    }
+
+    // O(1), the last value takes the place of the removed one
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.length,
+            "removal index {} is out of bounds for length {}",
+            index,
+            self.length
+        );
+        self.length -= 1;
+        unsafe {
+            let at = self.buf.as_ptr().add(index);
+            let value = ptr::read(at);
+            ptr::copy(self.buf.as_ptr().add(self.length), at, 1);
+            value
+        }
+    }
+
+    // drops everything from `len` on, the capacity stays
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.length {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.buf.as_ptr().add(len) }, self.length - len);
+        // the length goes first, so a panicking drop can't lead to a double drop
+        self.length = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    // makes room for at least `additional` more values without another allocation
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .length
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.cap {
+            self.grow(needed);
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        if !Self::is_zst() && self.cap > self.length {
+            self.resize_buffer(self.length);
+        }
+    }
+
+    pub fn at(&self, index: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get(index).cloned()
+    }
+
+    // owning, `.rev()` walks from the last value to the first
+    pub fn back_iter(self) -> IntoIter<T> {
+        self.into_iter()
+    }
+
+    pub fn iter(&self) -> ArrayIterator<'_, T> {
+        ArrayIterator { data: self }
+    }
+
+    pub fn iter_mut(&mut self) -> ArrayIteratorMut<'_, T> {
+        ArrayIteratorMut { data: self }
+    }
+
+    fn is_zst() -> bool {
+        std::mem::size_of::<T>() == 0
+    }
+
+    fn grow(&mut self, min_cap: usize) {
+        // only zero sized types start out with usize::MAX, there is no more room for them
+        assert!(!Self::is_zst(), "capacity overflow");
+        // a policy can't hand out less than what is needed
+        let new_cap = self.policy.grow(self.cap, min_cap).max(min_cap);
+        self.resize_buffer(new_cap);
+    }
+
+    // moves the values to a buffer of `new_cap`, which is at least `length`
+    fn resize_buffer(&mut self, new_cap: usize) {
+        let new_layout = Layout::array::<T>(new_cap).expect("capacity overflow");
+        let old_layout = Layout::array::<T>(self.cap).expect("capacity overflow");
+        let old_buf = self.buf.as_ptr() as *mut u8;
+        self.buf = if new_cap == 0 {
+            unsafe { alloc::dealloc(old_buf, old_layout) };
+            NonNull::dangling()
+        } else {
+            let new_buf = if self.cap == 0 {
+                unsafe { alloc::alloc(new_layout) }
+            } else {
+                unsafe { alloc::realloc(old_buf, old_layout, new_layout.size()) }
+            };
+            if self.cap != 0 && new_buf != old_buf {
+                self.stats.bytes_copied += (self.length * std::mem::size_of::<T>()) as u64;
+            }
+            NonNull::new(new_buf as *mut T).unwrap_or_else(|| alloc::handle_alloc_error(new_layout))
+        };
+        self.stats.reallocations += 1;
+        self.cap = new_cap;
     }
 }
 
-impl IntoIterator for TimestampSaver {
-    type Item = u64; 
-    type IntoIter = ArrayIterator;
+// Queries for timestamp logs. They binary search, so the timestamps have to be in ascending order,
+// as they are when every reading is appended as it arrives. Ranges include `from` and exclude `to`.
+impl DynamicArray<u64> {
+    // index of the first timestamp >= ts, len() if there is none
+    pub fn lower_bound(&self, ts: u64) -> usize {
+        self.partition_point(|t| *t < ts)
+    }
+
+    // index of the first timestamp > ts, len() if there is none
+    pub fn upper_bound(&self, ts: u64) -> usize {
+        self.partition_point(|t| *t <= ts)
+    }
+
+    pub fn range(&self, from_ts: u64, to_ts: u64) -> &[u64] {
+        let start = self.lower_bound(from_ts);
+        let end = self.lower_bound(to_ts).max(start);
+        &self[start..end]
+    }
+
+    pub fn count_between(&self, from_ts: u64, to_ts: u64) -> usize {
+        self.range(from_ts, to_ts).len()
+    }
+
+    // (start of the interval, number of timestamps in it) for every interval from the first to the
+    // last timestamp, empty ones included; intervals start at multiples of `interval`
+    pub fn rate_per_interval(&self, interval: u64) -> Vec<(u64, usize)> {
+        assert!(interval > 0, "interval has to be at least 1");
+        let (first, last) = match (self.first(), self.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return vec![],
+        };
+        let mut rates = vec![];
+        let mut start = first - first % interval;
+        let mut index = 0;
+        loop {
+            let end = self.lower_bound(start.saturating_add(interval)).max(index);
+            rates.push((start, end - index));
+            index = end;
+            match start.checked_add(interval) {
+                Some(next) if next <= last => start = next,
+                _ => break,
+            }
+        }
+        rates
+    }
+
+    // pairs of neighbouring timestamps that are more than `threshold` apart
+    pub fn gaps(&self, threshold: u64) -> Vec<(u64, u64)> {
+        self.windows(2)
+            .filter(|w| w[1] - w[0] > threshold)
+            .map(|w| (w[0], w[1]))
+            .collect()
+    }
+
+    pub fn min_inter_arrival(&self) -> Option<u64> {
+        self.windows(2).map(|w| w[1] - w[0]).min()
+    }
+
+    pub fn max_inter_arrival(&self) -> Option<u64> {
+        self.windows(2).map(|w| w[1] - w[0]).max()
+    }
+}
+
+impl<T> Drop for DynamicArray<T> {
+    fn drop(&mut self) {
+        self.clear();
+        if !Self::is_zst() && self.cap != 0 {
+            let layout = Layout::array::<T>(self.cap).expect("capacity overflow");
+            unsafe { alloc::dealloc(self.buf.as_ptr() as *mut u8, layout) };
+        }
+    }
+}
+
+impl<T> Default for DynamicArray<T> {
+    fn default() -> DynamicArray<T> {
+        DynamicArray::new_empty()
+    }
+}
+
+impl<T> Deref for DynamicArray<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr(), self.length) }
+    }
+}
+
+impl<T> DerefMut for DynamicArray<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buf.as_ptr(), self.length) }
+    }
+}
+
+impl<T, I: SliceIndex<[T]>> Index<I> for DynamicArray<T> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        Index::index(&**self, index)
+    }
+}
+
+impl<T, I: SliceIndex<[T]>> IndexMut<I> for DynamicArray<T> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        IndexMut::index_mut(&mut **self, index)
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for DynamicArray<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(&**self).finish()
+    }
+}
+
+impl<T> IntoIterator for DynamicArray<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        // the iterator moves the values out itself, the array only frees the buffer in the end
+        let back = self.length;
+        self.length = 0;
+        IntoIter {
+            array: self,
+            front: 0,
+            back,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a DynamicArray<T> {
+    type Item = &'a T;
+    type IntoIter = ArrayIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut DynamicArray<T> {
+    type Item = &'a mut T;
+    type IntoIter = ArrayIteratorMut<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        ArrayIterator::new(0, self.buf)
+        self.iter_mut()
     }
 }
 
-pub struct ArrayIterator {
-    current: usize, 
-    data: Box<[Node]>, 
+// both ends shrink the remaining slice, so they stop when they meet
+pub struct ArrayIterator<'a, T> {
+    data: &'a [T],
 }
 
-impl ArrayIterator {
-    fn new(index: usize, buf: Box<[Node]>) -> ArrayIterator {
-        ArrayIterator { current: index, data: buf }
+impl<'a, T> Iterator for ArrayIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let (first, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.data.len(), Some(self.data.len()))
     }
 }
 
-impl Iterator for ArrayIterator {
-    type Item = u64; 
+impl<'a, T> DoubleEndedIterator for ArrayIterator<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        let (last, rest) = self.data.split_last()?;
+        self.data = rest;
+        Some(last)
+    }
+}
 
-    fn next(&mut self) -> Option<u64> {
-        if self.current < self.data.len() {
-            let item = self.data[self.current]; 
-            self.current += 1; 
-            item 
-        } else {
-            None 
+impl<'a, T> ExactSizeIterator for ArrayIterator<'a, T> {}
+
+pub struct ArrayIteratorMut<'a, T> {
+    data: &'a mut [T],
+}
+
+impl<'a, T> Iterator for ArrayIteratorMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        // take the slice out, so the split parts can keep the full lifetime
+        let (first, rest) = std::mem::take(&mut self.data).split_first_mut()?;
+        self.data = rest;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.data.len(), Some(self.data.len()))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ArrayIteratorMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        let (last, rest) = std::mem::take(&mut self.data).split_last_mut()?;
+        self.data = rest;
+        Some(last)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ArrayIteratorMut<'a, T> {}
+
+pub struct IntoIter<T> {
+    // length 0, the values in front..back are still there and owned by the iterator
+    array: DynamicArray<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
         }
+        let value = unsafe { ptr::read(self.array.buf.as_ptr().add(self.front)) };
+        self.front += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.back - self.front, Some(self.back - self.front))
     }
 }
 
-impl DoubleEndedIterator for ArrayIterator {
-    fn next_back(&mut self) -> Option<u64> {
-        if self.current < self.data.len() {
-            let item = self.data[self.current]; 
-            if self.current == 0 {
-                self.current = self.data.len() - 1; 
-            } else {
-                self.current -= 1; 
-            }
-            item 
-        } else {
-            None 
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
         }
+        self.back -= 1;
+        Some(unsafe { ptr::read(self.array.buf.as_ptr().add(self.back)) })
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    // drops what was not taken, the array then frees the buffer
+    fn drop(&mut self) {
+        let rest = ptr::slice_from_raw_parts_mut(
+            unsafe { self.array.buf.as_ptr().add(self.front) },
+            self.back - self.front,
+        );
+        self.front = self.back;
+        unsafe { ptr::drop_in_place(rest) };
     }
 }
@@ -1,17 +1,29 @@
 #![feature(test)]
 extern crate test;
 
+mod arena_list;
+mod compressed_timestamps;
+mod concurrent_log;
+mod concurrent_skip_list;
 mod double_list;
+mod durable_log;
+mod lsm_store;
+mod mapped_array;
+mod persistent_list;
+mod persistent_vector;
 mod single_list;
 mod skip_list;
+mod unrolled_list;
 
 mod dynamic_array;
+mod ring_buffer;
 
 #[cfg(test)]
 mod tests {
     use crate::*;
     use rand::thread_rng;
     use rand::Rng;
+    use std::ops::Bound;
     use test::Bencher;
     const LIST_ITEMS: u64 = 15_000;
 
@@ -40,6 +52,1289 @@ mod tests {
         });
     }
 
+    // the same appends with the other growth policies, see DynamicArray::stats for their cost
+    fn bench_dynamic_array_append_with(
+        b: &mut Bencher,
+        policy: impl dynamic_array::GrowthPolicy + 'static,
+    ) {
+        let mut arr = dynamic_array::TimestampSaver::with_policy(policy);
+        let mut rng = thread_rng();
+
+        b.iter(|| arr.append(rng.gen::<u64>()));
+    }
+
+    #[bench]
+    fn bench_dynamic_array_append_doubling(b: &mut Bencher) {
+        bench_dynamic_array_append_with(b, dynamic_array::Doubling);
+    }
+
+    #[bench]
+    fn bench_dynamic_array_append_fixed_chunk(b: &mut Bencher) {
+        bench_dynamic_array_append_with(b, dynamic_array::FixedChunk(4_096));
+    }
+
+    #[bench]
+    fn bench_dynamic_array_append_exact(b: &mut Bencher) {
+        bench_dynamic_array_append_with(b, dynamic_array::Exact);
+    }
+
+    // full scans, the unrolled list reads one node per 64 values
+    #[bench]
+    fn bench_double_list_scan(b: &mut Bencher) {
+        let mut list = double_list::TransactionLog::new_empty();
+        for i in 0..LIST_ITEMS {
+            list.append(i);
+        }
+
+        b.iter(|| list.iter().sum::<u64>());
+    }
+
+    #[bench]
+    fn bench_unrolled_list_scan(b: &mut Bencher) {
+        let mut list = unrolled_list::TransactionLog::new_empty();
+        for i in 0..LIST_ITEMS {
+            list.append(i);
+        }
+
+        b.iter(|| list.iter().sum::<u64>());
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Transaction {
+        id: u64,
+        amount: i64,
+    }
+
+    #[test]
+    fn double_list_generic_payload() {
+        let mut log = double_list::TransactionLog::new_empty();
+        for i in 0..5 {
+            log.append(Transaction {
+                id: i,
+                amount: i as i64 * 10,
+            });
+        }
+        assert_eq!(log.length, 5);
+        assert_eq!(log.iter().map(|t| t.id).collect::<Vec<u64>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(log.pop(), Some(Transaction { id: 0, amount: 0 }));
+        assert_eq!(log.length, 4);
+
+        let back: Vec<u64> = log.back_iter().rev().map(|t| t.id).collect();
+        assert_eq!(back, vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn double_list_borrowing_iterators() {
+        let mut log = double_list::TransactionLog::new_empty();
+        for i in 0..5 {
+            log.append(format!("tx {}", i));
+        }
+        let mut iter = log.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next().map(|s| s.as_str()), Some("tx 0"));
+        assert_eq!(iter.next_back().map(|s| s.as_str()), Some("tx 4"));
+        assert_eq!(iter.next().map(|s| s.as_str()), Some("tx 1"));
+        assert_eq!(iter.next_back().map(|s| s.as_str()), Some("tx 3"));
+        assert_eq!(iter.next().map(|s| s.as_str()), Some("tx 2"));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+
+        for tx in log.iter_mut().rev().take(2) {
+            tx.push_str(" amended");
+        }
+        let back: Vec<&String> = log.back_iter().rev().collect();
+        assert_eq!(back[0], "tx 4 amended");
+        assert_eq!(back[1], "tx 3 amended");
+        assert_eq!(back[2], "tx 2");
+
+        // nothing is borrowed anymore, so popping is fine
+        assert_eq!(log.pop(), Some("tx 0".to_string()));
+        assert_eq!(log.into_iter().rev().count(), 4);
+    }
+
+    #[test]
+    fn double_list_cursor_insert_remove() {
+        let mut log = double_list::TransactionLog::new_empty();
+        for i in 0..5 {
+            log.append(i);
+        }
+        {
+            let mut cursor = log.cursor_front_mut();
+            cursor.move_next();
+            cursor.move_next();
+            assert_eq!(cursor.index(), Some(2));
+            assert_eq!(*cursor.current().unwrap(), 2);
+            assert_eq!(*cursor.peek_prev().unwrap(), 1);
+            assert_eq!(*cursor.peek_next().unwrap(), 3);
+
+            *cursor.current_mut().unwrap() = 20;
+            cursor.insert_before(15);
+            cursor.insert_after(25);
+            assert_eq!(cursor.index(), Some(3));
+            assert_eq!(cursor.remove_current(), Some(20));
+            assert_eq!(*cursor.current().unwrap(), 25);
+
+            cursor.move_prev();
+            cursor.move_prev();
+            cursor.move_prev();
+            cursor.move_prev();
+            assert!(cursor.current().is_none());
+            cursor.move_prev();
+            assert_eq!(cursor.index(), Some(5));
+            assert_eq!(cursor.remove_current(), Some(4));
+            assert!(cursor.current().is_none());
+        }
+        assert_eq!(log.length, 5);
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![0, 1, 15, 25, 3]);
+    }
+
+    #[test]
+    fn double_list_cursor_split() {
+        let mut log = double_list::TransactionLog::new_empty();
+        for i in 0..6 {
+            log.append(i);
+        }
+        let (before, after) = {
+            let mut cursor = log.cursor_back_mut();
+            cursor.move_prev();
+            cursor.move_prev();
+            let after = cursor.split_after();
+            let before = cursor.split_before();
+            assert_eq!(cursor.index(), Some(0));
+            (before, after)
+        };
+        assert_eq!((before.length, log.length, after.length), (3, 1, 2));
+        assert_eq!(before.iter().copied().collect::<Vec<i32>>(), vec![0, 1, 2]);
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![3]);
+        assert_eq!(after.back_iter().rev().copied().collect::<Vec<i32>>(), vec![5, 4]);
+    }
+
+    #[test]
+    fn double_list_nested_checkpoints() {
+        let mut log = double_list::TransactionLog::new_empty();
+        log.append(1);
+        let outer = log.checkpoint();
+        log.append(2);
+        assert_eq!(log.pop(), Some(1));
+        let inner = log.checkpoint();
+        log.append(3);
+        log.append(4);
+
+        assert!(log.rollback_to(inner));
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![2]);
+        log.append(5);
+        assert!(log.rollback_to(inner));
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![2]);
+
+        assert!(log.rollback_to(outer));
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![1]);
+        assert!(!log.rollback_to(inner));
+        assert!(log.release(outer));
+        assert!(!log.rollback_to(outer));
+        assert_eq!(log.length, 1);
+        // releasing the last checkpoint stops recording changes
+        log.append(6);
+        assert!(!log.undo());
+    }
+
+    #[test]
+    fn double_list_undo_redo() {
+        let mut log = double_list::TransactionLog::new_with_history();
+        log.append("a".to_string());
+        log.append("b".to_string());
+        assert_eq!(log.pop(), Some("a".to_string()));
+
+        assert!(log.undo());
+        assert_eq!(log.iter().next().map(|s| s.as_str()), Some("a"));
+        assert!(log.undo());
+        assert_eq!(log.length, 1);
+        assert!(log.redo());
+        assert!(log.redo());
+        assert_eq!(log.iter().cloned().collect::<Vec<String>>(), vec!["b"]);
+        assert!(!log.redo());
+
+        // a new change drops what could have been redone
+        assert!(log.undo());
+        log.append("c".to_string());
+        assert!(!log.redo());
+        assert_eq!(log.length, 3);
+
+        // cursor changes are undone by index
+        assert_eq!(
+            log.cursor_front_mut().remove_current(),
+            Some("a".to_string())
+        );
+        assert!(log.undo());
+        assert_eq!(log.iter().next().map(|s| s.as_str()), Some("a"));
+        assert!(log.redo());
+        assert_eq!(log.length, 2);
+
+        // a log created with history keeps it after its checkpoints are released
+        let checkpoint = log.checkpoint();
+        log.append("d".to_string());
+        assert!(log.release(checkpoint));
+        assert!(log.undo());
+        assert_eq!(log.length, 2);
+    }
+
+    #[test]
+    fn double_list_rollback_cursor_changes() {
+        let mut log = double_list::TransactionLog::new_empty();
+        for i in 1..=3 {
+            log.append(i);
+        }
+        let checkpoint = log.checkpoint();
+        {
+            let mut cursor = log.cursor_back_mut();
+            cursor.insert_after(4);
+            cursor.move_prev();
+            *cursor.current_mut().unwrap() = 20;
+            cursor.insert_before(15);
+            cursor.move_next();
+            assert_eq!(cursor.remove_current(), Some(3));
+        }
+        for value in log.iter_mut().rev().take(2) {
+            *value *= 10;
+        }
+        log.append(5);
+        assert_eq!(
+            log.iter().copied().collect::<Vec<i32>>(),
+            vec![1, 15, 200, 40, 5]
+        );
+
+        assert!(log.rollback_to(checkpoint));
+        assert_eq!(log.iter().copied().collect::<Vec<i32>>(), vec![1, 2, 3]);
+        assert!(log.release(checkpoint));
+    }
+
+    #[test]
+    #[should_panic(expected = "release the open checkpoints first")]
+    fn double_list_split_with_open_checkpoint() {
+        let mut log = double_list::TransactionLog::new_empty();
+        log.append(1);
+        log.append(2);
+        let _checkpoint = log.checkpoint();
+        log.cursor_front_mut().split_after();
+    }
+
+    #[test]
+    fn arena_list_pop_with_live_iterator() {
+        let mut log = arena_list::TransactionLog::new_empty();
+        log.append("a".to_string());
+        log.append("b".to_string());
+        let first = log.iter().next().cloned();
+        assert_eq!(first, Some("a".to_string()));
+        assert_eq!(log.pop(), Some("a".to_string()));
+        assert_eq!(log.pop(), Some("b".to_string()));
+        assert_eq!(log.pop(), None);
+        assert_eq!(log.length, 0);
+    }
+
+    #[test]
+    fn arena_list_handles_and_iteration() {
+        let mut log = arena_list::TransactionLog::new_empty();
+        let handles: Vec<arena_list::Handle> = (0..5).map(|i| log.append(i)).collect();
+        assert_eq!(log.remove(handles[2]), Some(2));
+        assert_eq!(log.remove(handles[2]), None);
+
+        // the freed slot is reused, but the old handle stays dead
+        let reused = log.append(10);
+        assert_eq!(log.get(handles[2]), None);
+        assert_eq!(log.get(reused), Some(&10));
+        *log.get_mut(handles[0]).unwrap() = 100;
+
+        let mut iter = log.iter();
+        assert_eq!(iter.next(), Some(&100));
+        assert_eq!(iter.next_back(), Some(&10));
+        assert_eq!(iter.collect::<Vec<&i32>>(), vec![&1, &3, &4]);
+        assert_eq!(log.back_iter().rev().count(), 5);
+        assert_eq!(log.into_iter().rev().collect::<Vec<i32>>(), vec![10, 4, 3, 1, 100]);
+    }
+
+    #[test]
+    fn concurrent_log_many_producers() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let log = Arc::new(concurrent_log::ConcurrentTransactionLog::new_bounded(16));
+        let producers: Vec<_> = (0..4_u64)
+            .map(|p| {
+                let log = log.clone();
+                thread::spawn(move || {
+                    for i in 0..1_000 {
+                        log.append(p * 1_000 + i);
+                    }
+                })
+            })
+            .collect();
+
+        let mut seen = vec![];
+        for _ in 0..4_000 {
+            seen.push(log.pop_wait());
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert!(log.is_empty());
+        assert_eq!(log.pop(), None);
+
+        // every producer's values come out in the order they went in
+        for p in 0..4 {
+            let mine: Vec<u64> = seen.iter().copied().filter(|v| v / 1_000 == p).collect();
+            assert_eq!(mine, (p * 1_000..(p + 1) * 1_000).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn concurrent_log_backpressure() {
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+
+        let log = Arc::new(concurrent_log::ConcurrentTransactionLog::new_bounded(2));
+        log.append("a".to_string());
+        log.append("b".to_string());
+        assert_eq!(log.try_append("c".to_string()), Err("c".to_string()));
+        assert_eq!(log.len(), 2);
+
+        let producer = {
+            let log = log.clone();
+            thread::spawn(move || log.append("c".to_string()))
+        };
+        assert_eq!(log.pop(), Some("a".to_string()));
+        producer.join().unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.pop_wait(), "b");
+        assert_eq!(log.pop_wait(), "c");
+        assert_eq!(log.pop_wait_timeout(Duration::from_millis(10)), None);
+    }
+
+    #[test]
+    fn concurrent_skip_list_many_writers() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let list = Arc::new(concurrent_skip_list::ConcurrentSkipList::new_empty(8));
+        let writers: Vec<_> = (0..4_u64)
+            .map(|w| {
+                let list = list.clone();
+                thread::spawn(move || {
+                    // every writer owns the keys k with k % 4 == w and removes the odd ones again
+                    for i in 0..500 {
+                        let key = i * 4 + w;
+                        assert_eq!(list.insert(key, format!("{}", key)), None);
+                        let expected = if key % 2 == 1 {
+                            assert_eq!(list.remove(&key), Some(format!("{}", key)));
+                            None
+                        } else {
+                            Some(format!("{}", key))
+                        };
+                        assert_eq!(list.find(key), expected);
+                        let seen: Vec<u64> = list.range(..10).map(|(k, _)| k).collect();
+                        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(list.len(), 1_000);
+        let keys: Vec<u64> = list.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, (0..2_000).step_by(2).collect::<Vec<u64>>());
+        assert_eq!(list.insert(10, "ten".to_string()), Some("10".to_string()));
+        assert_eq!(list.find(10), Some("ten".to_string()));
+        assert!(!list.contains_key(&11));
+        assert_eq!(list.remove(&11), None);
+        assert_eq!(
+            list.range((Bound::Excluded(10), Bound::Included(16)))
+                .map(|(k, _)| k)
+                .collect::<Vec<u64>>(),
+            vec![12, 14, 16]
+        );
+    }
+
+    #[test]
+    fn concurrent_skip_list_queries() {
+        let list = concurrent_skip_list::ConcurrentSkipList::new_seeded(6, 0.25, 7);
+        assert_eq!(list.first(), None);
+        assert_eq!(list.last(), None);
+        for key in (0..100).step_by(10) {
+            list.append(key, key * 2);
+        }
+        assert_eq!(list.get(&30), Some(60));
+        assert_eq!(list.get(&31), None);
+        assert_eq!(list.floor(&35), Some((30, 60)));
+        assert_eq!(list.floor(&30), Some((30, 60)));
+        assert_eq!(list.floor(&-1), None);
+        assert_eq!(list.ceiling(&35), Some((40, 80)));
+        assert_eq!(list.ceiling(&91), None);
+        assert_eq!(list.first(), Some((0, 0)));
+        assert_eq!(list.last(), Some((90, 180)));
+
+        // removed entries are skipped
+        assert_eq!(list.remove(&30), Some(60));
+        assert_eq!(list.remove(&90), Some(180));
+        assert_eq!(list.floor(&35), Some((20, 40)));
+        assert_eq!(list.ceiling(&25), Some((40, 80)));
+        assert_eq!(list.last(), Some((80, 160)));
+    }
+
+    #[test]
+    fn concurrent_skip_list_insert_remove_same_keys() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let list = Arc::new(concurrent_skip_list::ConcurrentSkipList::new_empty(4));
+        let workers: Vec<_> = (0..4_u64)
+            .map(|w| {
+                let list = list.clone();
+                thread::spawn(move || {
+                    // every value is written once, so it has to come back out exactly once:
+                    // replaced by an insert, taken by a remove or still in the list at the end
+                    let mut seen = vec![];
+                    for i in 0..5_000 {
+                        let key = i % 8;
+                        let value = (w << 32) | i;
+                        seen.extend(list.insert(key, value));
+                        if i % 2 == w % 2 {
+                            seen.extend(list.remove(&key));
+                        }
+                    }
+                    seen
+                })
+            })
+            .collect();
+        let mut seen: Vec<u64> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+
+        let rest: Vec<(u64, u64)> = list.iter().collect();
+        assert_eq!(list.len(), rest.len());
+        assert!(rest.windows(2).all(|w| w[0].0 < w[1].0));
+        seen.extend(rest.into_iter().map(|(_, value)| value));
+        seen.sort_unstable();
+        let written: Vec<u64> = (0..4_u64)
+            .flat_map(|w| (0..5_000).map(move |i| (w << 32) | i))
+            .collect();
+        assert_eq!(seen, written);
+    }
+
+    #[test]
+    fn persistent_list_snapshots() {
+        let empty = persistent_list::PersistentTransactionLog::new_empty();
+        let v1 = empty.append("a".to_string()).append("b".to_string());
+        let snapshot = v1.clone();
+        let v2 = v1.append("c".to_string());
+        let v3 = v1.append("d".to_string());
+
+        assert_eq!(snapshot.length, 2);
+        assert_eq!(snapshot.iter().collect::<Vec<&String>>(), vec!["b", "a"]);
+        assert_eq!(v2.iter().collect::<Vec<&String>>(), vec!["c", "b", "a"]);
+        assert_eq!(v3.last().map(|s| s.as_str()), Some("d"));
+        assert!(v2.previous().ptr_eq(&v3.previous()));
+        assert!(v2.previous().ptr_eq(&snapshot));
+        assert!(empty.iter().next().is_none());
+        assert_eq!(empty.previous().length, 0);
+
+        let reader = std::thread::spawn(move || snapshot.iter().count());
+        let mut writer = v2;
+        for i in 0..100_000 {
+            writer = writer.append(format!("insert {}", i));
+        }
+        assert_eq!(reader.join().unwrap(), 2);
+        assert_eq!(writer.length, 100_003);
+    }
+
+    fn drain_single_list(log: &mut single_list::TransactionLog) -> Vec<String> {
+        std::iter::from_fn(|| log.pop()).collect()
+    }
+
+    fn single_list_of(values: &[&str]) -> single_list::TransactionLog {
+        let mut log = single_list::TransactionLog::new_empty();
+        for v in values {
+            log.append(v.to_string());
+        }
+        log
+    }
+
+    #[test]
+    fn single_list_reverse_and_splice() {
+        let mut log = single_list_of(&["a", "b", "c"]);
+        log.reverse();
+        log.append_list(single_list_of(&["d", "e"]));
+        log.append("f".to_string());
+        assert_eq!(log.length, 6);
+
+        let mut rest = log.split_off(2);
+        assert_eq!((log.length, rest.length), (2, 4));
+        log.append("g".to_string());
+        assert_eq!(drain_single_list(&mut log), vec!["c", "b", "g"]);
+        assert_eq!(drain_single_list(&mut rest), vec!["a", "d", "e", "f"]);
+
+        let mut log = single_list_of(&["a"]);
+        let mut all = log.split_off(0);
+        assert_eq!(log.length, 0);
+        all.append_list(log.split_off(0));
+        assert_eq!(drain_single_list(&mut all), vec!["a"]);
+    }
+
+    #[test]
+    fn single_list_sort_and_dedup() {
+        let mut log = single_list_of(&["gw2 7", "gw1 3", "gw2 3", "gw1 1", "gw3 7", "gw1 3"]);
+        // sort by offset only, entries with the same offset keep their order
+        log.sort_by_key(|entry| entry.split(' ').nth(1).unwrap().parse::<u64>().unwrap());
+        log.append("gw4 9".to_string());
+        assert_eq!(log.length, 7);
+
+        log.dedup_by(|a, b| a.split(' ').nth(1) == b.split(' ').nth(1));
+        assert_eq!(log.length, 4);
+        log.append("gw5 10".to_string());
+        assert_eq!(
+            drain_single_list(&mut log),
+            vec!["gw1 1", "gw1 3", "gw2 7", "gw4 9", "gw5 10"]
+        );
+
+        let mut log = single_list_of(&["x", "x", "x"]);
+        log.dedup_by(|a, b| a == b);
+        log.append("y".to_string());
+        assert_eq!(drain_single_list(&mut log), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn skip_list_out_of_order_insert_and_remove() {
+        let mut list = skip_list::SkipList::new_empty(8);
+        let mut keys: Vec<u64> = (0..1_000).collect();
+        thread_rng().shuffle(&mut keys);
+        for k in &keys {
+            assert_eq!(list.insert(*k, format!("insert {}", k)), None);
+        }
+        assert_eq!(list.length, 1_000);
+        assert_eq!(
+            list.insert(10, "update 10".to_string()),
+            Some("insert 10".to_string())
+        );
+        assert_eq!(list.length, 1_000);
+
+        for k in keys.iter().filter(|k| *k % 2 == 1) {
+            assert_eq!(list.remove(k), Some(format!("insert {}", k)));
+        }
+        assert_eq!(list.remove(&1), None);
+        assert_eq!(list.length, 500);
+        assert!(list.contains_key(&998));
+        assert!(!list.contains_key(&999));
+
+        list.get_mut(&4).unwrap().push_str(" amended");
+        assert_eq!(list.get(&4).map(|s| s.as_str()), Some("insert 4 amended"));
+        assert_eq!(list.find(10), Some("update 10".to_string()));
+
+        let keys: Vec<u64> = list.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..1_000).step_by(2).collect::<Vec<u64>>());
+
+        // freed nodes are reused
+        list.insert(1, "insert 1".to_string());
+        assert_eq!(list.into_iter().nth(1), Some((1, "insert 1".to_string())));
+    }
+
+    #[test]
+    fn skip_list_range_floor_ceiling() {
+        let mut log = skip_list::BestTransactionLog::new_empty(8);
+        assert_eq!(log.first(), None);
+        assert_eq!(log.last(), None);
+        for i in (0..100).rev() {
+            log.append(i * 10, format!("insert {}", i * 10));
+        }
+
+        let offsets: Vec<u64> = log.range(45..90).map(|(k, _)| *k).collect();
+        assert_eq!(offsets, vec![50, 60, 70, 80]);
+        let offsets: Vec<u64> = log.range(40..=90).map(|(k, _)| *k).collect();
+        assert_eq!(offsets, vec![40, 50, 60, 70, 80, 90]);
+        let offsets: Vec<u64> = log
+            .range((Bound::Excluded(970), Bound::Unbounded))
+            .map(|(k, _)| *k)
+            .collect();
+        assert_eq!(offsets, vec![980, 990]);
+        assert_eq!(log.range(..0).count(), 0);
+        assert_eq!(log.range(..).count(), 100);
+
+        assert_eq!(log.floor(&45).map(|(k, _)| *k), Some(40));
+        assert_eq!(log.floor(&40).map(|(k, _)| *k), Some(40));
+        assert_eq!(
+            log.ceiling(&45).map(|(k, v)| (*k, v.as_str())),
+            Some((50, "insert 50"))
+        );
+        assert_eq!(log.floor(&5000).map(|(k, _)| *k), Some(990));
+        assert_eq!(log.ceiling(&991), None);
+        assert_eq!(log.first().map(|(k, _)| *k), Some(0));
+        assert_eq!(log.last().map(|(k, _)| *k), Some(990));
+    }
+
+    #[test]
+    fn skip_list_seeded_levels() {
+        let build = |seed: u64, promotion: f64| {
+            let mut list = skip_list::SkipList::new_seeded(12, promotion, seed);
+            for i in 0..2_000_u64 {
+                list.insert(i, ());
+            }
+            list.level_stats()
+        };
+        let stats = build(7, 0.5);
+        assert_eq!(stats, build(7, 0.5));
+        assert_eq!(stats.nodes_per_level[0], 2_000);
+        assert!(stats.nodes_per_level.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(stats.links, stats.nodes_per_level.iter().sum::<u64>());
+
+        // fewer promotions need fewer links
+        let sparse = build(7, 0.125);
+        assert!(sparse.links < stats.links);
+        assert!(sparse.nodes_per_level[1] < stats.nodes_per_level[1]);
+    }
+
+    #[test]
+    fn skip_list_to_dot() {
+        let mut log = skip_list::BestTransactionLog::new_seeded(6, 0.5, 3);
+        for offset in [30, 10, 20] {
+            log.insert(offset, format!("insert <{}>", offset));
+        }
+        let dot = log.to_dot();
+        assert!(dot.starts_with("digraph skip_list {"));
+        assert!(dot.contains("head:l0:e -> n1:l0:w [label=\"1\"];"));
+        assert!(dot.contains("n3:l0:e -> nil:l0:w [label=\"1\"];"));
+        assert!(dot.contains("<td port=\"l0\">20</td>"));
+
+        // one edge per link, plus the head link on every level that is drawn
+        let stats = log.level_stats();
+        let levels = stats.nodes_per_level.iter().filter(|n| **n > 0).count();
+        assert_eq!(
+            dot.matches(" -> ").count() as u64,
+            stats.links + levels as u64
+        );
+    }
+
+    #[test]
+    fn skip_list_positional_access() {
+        let mut log = skip_list::BestTransactionLog::new_seeded(10, 0.5, 42);
+        let mut model: Vec<u64> = vec![];
+        let mut rng = thread_rng();
+        for _ in 0..2_000 {
+            let offset = rng.gen_range::<u64>(0, 5_000);
+            log.insert(offset, format!("insert {}", offset));
+            if let Err(pos) = model.binary_search(&offset) {
+                model.insert(pos, offset);
+            }
+        }
+        for _ in 0..500 {
+            let index = rng.gen_range::<usize>(0, model.len());
+            let offset = model.remove(index);
+            assert_eq!(
+                log.remove_at(index),
+                Some((offset, format!("insert {}", offset)))
+            );
+            let offset = rng.gen_range::<u64>(0, 5_000);
+            if log.remove(&offset).is_some() {
+                model.retain(|o| *o != offset);
+            }
+        }
+
+        assert_eq!(log.length as usize, model.len());
+        for (i, offset) in model.iter().enumerate() {
+            assert_eq!(log.nth(i).map(|(k, _)| *k), Some(*offset));
+            assert_eq!(log.rank(offset), i);
+            assert_eq!(log.rank(&(offset + 1)), i + 1);
+        }
+        assert_eq!(log.nth(model.len()), None);
+        assert_eq!(log.remove_at(model.len()), None);
+        assert_eq!(log.rank(&u64::MAX), model.len());
+    }
+
+    #[test]
+    fn lsm_store_reads_through_memtable_and_tables() {
+        let dir = durable_log_dir("lsm");
+        let config = lsm_store::StoreConfig {
+            memtable_bytes: 256,
+            index_interval: 4,
+            compaction_trigger: 3,
+        };
+        {
+            let mut store = lsm_store::LsmStore::open(&dir, config).unwrap();
+            for i in 0..200_u64 {
+                store.put(i, format!("v{}", i)).unwrap();
+            }
+            // overwrite and delete keys that have been flushed already
+            for i in (0..200_u64).step_by(10) {
+                store.put(i, format!("w{}", i)).unwrap();
+                store.delete(i + 1).unwrap();
+            }
+            assert_eq!(store.get(&10).unwrap(), Some("w10".to_string()));
+            assert_eq!(store.get(&11).unwrap(), None);
+            assert_eq!(store.get(&12).unwrap(), Some("v12".to_string()));
+            assert_eq!(store.get(&500).unwrap(), None);
+
+            store.compact().unwrap();
+            assert_eq!(store.table_count(), 1);
+            assert_eq!(store.get(&190).unwrap(), Some("w190".to_string()));
+            assert_eq!(store.get(&191).unwrap(), None);
+            store.put(500, "new".to_string()).unwrap();
+        }
+        let store = lsm_store::LsmStore::<u64, String>::open(&dir, config).unwrap();
+        for i in 0..200_u64 {
+            let expected = match i % 10 {
+                0 => Some(format!("w{}", i)),
+                1 => None,
+                _ => Some(format!("v{}", i)),
+            };
+            assert_eq!(store.get(&i).unwrap(), expected);
+        }
+        assert_eq!(store.get(&500).unwrap(), Some("new".to_string()));
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mapped_array_reopens_without_parsing() {
+        let dir = durable_log_dir("mapped");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timestamps.bin");
+        {
+            let mut saver = mapped_array::MappedTimestampSaver::open(&path).unwrap();
+            for ts in 0..1_000 {
+                saver.append(ts * 3).unwrap();
+            }
+            assert!(saver.capacity() >= 1_000);
+            saver.flush().unwrap();
+            assert_eq!(std::fs::read(&path).unwrap()[16..24], 1_000_u64.to_ne_bytes());
+            // the header only counts appends after a flush
+            saver.append(3_000).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap()[16..24], 1_000_u64.to_ne_bytes());
+        }
+        let mut saver = mapped_array::MappedTimestampSaver::open(&path).unwrap();
+        assert_eq!(saver.len(), 1_001);
+        assert_eq!(saver.at(999), Some(2_997));
+        assert_eq!(saver.at(1_001), None);
+        assert_eq!(saver.iter().rev().nth(1), Some(&2_997));
+        saver.append(5_000).unwrap();
+        drop(saver);
+        let saver = mapped_array::MappedTimestampSaver::open(&path).unwrap();
+        assert_eq!(saver.at(1_001), Some(5_000));
+        drop(saver);
+
+        // a newer format version is refused instead of being misread
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[8..16].copy_from_slice(&2_u64.to_ne_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let err = mapped_array::MappedTimestampSaver::open(&path)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        std::fs::write(&path, b"not a timestamp file, just text").unwrap();
+        let err = mapped_array::MappedTimestampSaver::open(&path)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn durable_log_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ch04-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn durable_log_replays_appends_and_pops() {
+        let dir = durable_log_dir("replay");
+        let config = durable_log::LogConfig {
+            max_segment_bytes: 64,
+            ..durable_log::LogConfig::new()
+        };
+        {
+            let mut log = durable_log::DurableTransactionLog::open(&dir, config).unwrap();
+            for i in 0..10 {
+                log.append(format!("insert {}", i)).unwrap();
+            }
+            assert_eq!(log.pop().unwrap(), Some("insert 0".to_string()));
+            assert_eq!(log.pop().unwrap(), Some("insert 1".to_string()));
+            assert!(log.segment_count() > 1);
+        }
+        let mut log = durable_log::DurableTransactionLog::<String>::open(&dir, config).unwrap();
+        assert_eq!(log.len(), 8);
+        assert_eq!(log.iter().next().map(|s| s.as_str()), Some("insert 2"));
+        log.append("insert 10".to_string()).unwrap();
+        assert_eq!(
+            log.iter().next_back().map(|s| s.as_str()),
+            Some("insert 10")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn durable_log_truncates_torn_tail() {
+        use std::io::Write;
+
+        let dir = durable_log_dir("torn");
+        {
+            let mut log =
+                durable_log::DurableTransactionLog::open(&dir, durable_log::LogConfig::new())
+                    .unwrap();
+            log.append(1_u64).unwrap();
+            log.append(2_u64).unwrap();
+        }
+        let segment = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let clean_len = std::fs::metadata(&segment).unwrap().len();
+        // half a record, as if the process died in the middle of a write
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&segment)
+            .unwrap();
+        file.write_all(&[21, 0, 0, 0, 1, 2]).unwrap();
+
+        let mut log =
+            durable_log::DurableTransactionLog::<u64>::open(&dir, durable_log::LogConfig::new())
+                .unwrap();
+        assert_eq!(std::fs::metadata(&segment).unwrap().len(), clean_len);
+        assert_eq!(log.iter().copied().collect::<Vec<u64>>(), vec![1, 2]);
+        log.append(3).unwrap();
+        drop(log);
+
+        let log =
+            durable_log::DurableTransactionLog::<u64>::open(&dir, durable_log::LogConfig::new())
+                .unwrap();
+        assert_eq!(log.iter().copied().collect::<Vec<u64>>(), vec![1, 2, 3]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn durable_log_rejects_corrupt_record_before_tail() {
+        let dir = durable_log_dir("corrupt");
+        {
+            let mut log =
+                durable_log::DurableTransactionLog::open(&dir, durable_log::LogConfig::new())
+                    .unwrap();
+            for i in 0..100_u64 {
+                log.append(i).unwrap();
+            }
+        }
+        let segment = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        // a u64 append record is 8 bytes of header, a kind byte, the offset and the value
+        let mut bytes = std::fs::read(&segment).unwrap();
+        bytes[25 + 20] ^= 0xff;
+        std::fs::write(&segment, &bytes).unwrap();
+
+        let err =
+            durable_log::DurableTransactionLog::<u64>::open(&dir, durable_log::LogConfig::new())
+                .err()
+                .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(std::fs::read(&segment).unwrap(), bytes);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn durable_log_rejects_records_of_another_type() {
+        let dir = durable_log_dir("wrong-type");
+        {
+            let mut log =
+                durable_log::DurableTransactionLog::open(&dir, durable_log::LogConfig::new())
+                    .unwrap();
+            log.append("insert 10".to_string()).unwrap();
+            log.append("insert 11".to_string()).unwrap();
+        }
+
+        let err =
+            durable_log::DurableTransactionLog::<u64>::open(&dir, durable_log::LogConfig::new())
+                .err()
+                .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let log =
+            durable_log::DurableTransactionLog::<String>::open(&dir, durable_log::LogConfig::new())
+                .unwrap();
+        assert_eq!(log.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn durable_log_segment_retention() {
+        let dir = durable_log_dir("retention");
+        let config = durable_log::LogConfig {
+            max_segment_bytes: 1,
+            retention: durable_log::Retention::Segments(3),
+            sync_on_write: false,
+        };
+        let mut log = durable_log::DurableTransactionLog::open(&dir, config).unwrap();
+        for i in 0..10_u64 {
+            log.append(i).unwrap();
+        }
+        // every record gets its own segment, only the last three survive
+        assert_eq!(log.segment_count(), 3);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        assert_eq!(log.iter().copied().collect::<Vec<u64>>(), vec![7, 8, 9]);
+        drop(log);
+
+        let log = durable_log::DurableTransactionLog::<u64>::open(&dir, config).unwrap();
+        assert_eq!(log.iter().copied().collect::<Vec<u64>>(), vec![7, 8, 9]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dynamic_array_generic_operations() {
+        let mut arr = dynamic_array::DynamicArray::new_empty();
+        for i in 0..20 {
+            arr.push(format!("ts {}", i));
+        }
+        assert_eq!(arr.pop(), Some("ts 19".to_string()));
+        arr.insert(0, "first".to_string());
+        assert_eq!(arr.remove(1), "ts 0");
+        assert_eq!(arr.swap_remove(0), "first");
+        assert_eq!(arr[0], "ts 18");
+        arr[1].push_str(" amended");
+        assert_eq!(&arr[1..3], &["ts 1 amended", "ts 2"]);
+
+        // slice methods through deref
+        arr.sort();
+        assert_eq!(arr.first().map(|s| s.as_str()), Some("ts 1 amended"));
+        assert!(arr.contains(&"ts 18".to_string()));
+
+        arr.truncate(5);
+        assert_eq!(arr.len(), 5);
+        arr.reserve(100);
+        assert!(arr.capacity() >= 105);
+        arr.shrink_to_fit();
+        assert_eq!(arr.capacity(), 5);
+        arr.clear();
+        arr.shrink_to_fit();
+        assert!(arr.is_empty());
+        arr.push("again".to_string());
+        assert_eq!(arr.at(0), Some("again".to_string()));
+    }
+
+    #[test]
+    fn dynamic_array_drops_values() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        {
+            let mut arr = dynamic_array::DynamicArray::new_empty();
+            for _ in 0..50 {
+                arr.push(value.clone());
+            }
+            assert_eq!(Rc::strong_count(&value), 51);
+            drop(arr.pop());
+            arr.truncate(10);
+            assert_eq!(Rc::strong_count(&value), 11);
+        }
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        // zero sized values never allocate
+        let mut units = dynamic_array::DynamicArray::new_empty();
+        for _ in 0..1_000 {
+            units.push(());
+        }
+        assert_eq!(units.len(), 1_000);
+        assert_eq!(units.capacity(), usize::MAX);
+    }
+
+    #[test]
+    fn dynamic_array_bounded_iterators() {
+        let mut arr = dynamic_array::TimestampSaver::new_empty();
+        for i in 0..5 {
+            arr.append(i);
+        }
+        let mut iter = arr.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.rev().collect::<Vec<&u64>>(), vec![&3, &2, &1]);
+
+        for ts in arr.iter_mut().rev().take(2) {
+            *ts += 10;
+        }
+        assert_eq!(&arr[..], &[0, 1, 2, 13, 14]);
+        for ts in &mut arr {
+            *ts *= 2;
+        }
+        assert_eq!((&arr).into_iter().sum::<u64>(), 60);
+
+        // only the values, not the spare capacity
+        assert!(arr.capacity() > arr.len());
+        let mut owned = arr.into_iter();
+        assert_eq!(owned.len(), 5);
+        assert_eq!(owned.next_back(), Some(28));
+        assert_eq!(owned.collect::<Vec<u64>>(), vec![0, 2, 4, 26]);
+
+        let empty = dynamic_array::TimestampSaver::new_empty();
+        assert_eq!(empty.back_iter().next_back(), None);
+    }
+
+    #[test]
+    fn dynamic_array_into_iter_drops_rest() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut arr = dynamic_array::DynamicArray::new_empty();
+        for _ in 0..10 {
+            arr.push(value.clone());
+        }
+        let mut owned = arr.into_iter();
+        let first = owned.next();
+        assert_eq!(Rc::strong_count(&value), 11);
+        drop(owned);
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(first);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn ring_buffer_rolling_window() {
+        let mut window = ring_buffer::TimestampWindow::new_bounded(4);
+        for ts in 0..6 {
+            window.push_back(ts * 10);
+        }
+        assert!(window.is_full());
+        assert_eq!(window.push_back(60), Some(20));
+        assert_eq!(
+            window.iter().copied().collect::<Vec<u64>>(),
+            vec![30, 40, 50, 60]
+        );
+        assert_eq!((window[0], window[3]), (30, 60));
+        assert_eq!(window.get(4), None);
+
+        // the buffer has wrapped, iteration still goes oldest to newest from both ends
+        let (first, second) = window.as_slices();
+        assert!(!second.is_empty());
+        assert_eq!(first.len() + second.len(), 4);
+        let mut iter = window.iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next_back(), Some(&60));
+        assert_eq!(iter.next(), Some(&30));
+        assert_eq!(iter.rev().collect::<Vec<&u64>>(), vec![&50, &40]);
+
+        assert_eq!(window.push_front(25), Some(60));
+        assert_eq!(window.pop_back(), Some(50));
+        assert_eq!(window.pop_front(), Some(25));
+        window[0] += 1;
+        for ts in window.iter_mut().rev() {
+            *ts += 1;
+        }
+        assert_eq!(window.into_iter().rev().collect::<Vec<u64>>(), vec![41, 32]);
+    }
+
+    #[test]
+    fn ring_buffer_drops_overwritten_values() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        {
+            let mut ring = ring_buffer::RingBuffer::new_bounded(3);
+            for _ in 0..10 {
+                ring.push_back(value.clone());
+            }
+            assert_eq!(Rc::strong_count(&value), 4);
+            let mut owned = ring.into_iter();
+            owned.next();
+            assert_eq!(Rc::strong_count(&value), 3);
+        }
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn compressed_timestamps_round_trip() {
+        let mut rng = thread_rng();
+        let mut expected = vec![];
+        let mut ts = 1_600_000_000_000_u64;
+        for _ in 0..10_000 {
+            // one reading per second, give or take a few milliseconds
+            ts += 1_000 + rng.gen_range::<u64>(0, 8);
+            expected.push(ts);
+        }
+        let column: compressed_timestamps::CompressedTimestamps =
+            expected.iter().copied().collect();
+        assert_eq!(column.len(), expected.len());
+        assert_eq!(column.iter().len(), expected.len());
+        assert!(column.iter().eq(expected.iter().copied()));
+        for i in [0, 1, 127, 128, 129, 5_000, 9_999] {
+            assert_eq!(column.at(i), Some(expected[i]));
+        }
+        assert_eq!(column.at(10_000), None);
+        assert_eq!(column.last(), Some(ts));
+        assert!(column.compressed_bytes() * 4 < column.len() * 8);
+
+        // out of order and extreme values still come back exactly
+        let odd = vec![5, 3, u64::MAX, 0, 42, 42, 1 << 63];
+        let column: compressed_timestamps::CompressedTimestamps = odd.iter().copied().collect();
+        assert_eq!(column.iter().collect::<Vec<u64>>(), odd);
+    }
+
+    #[test]
+    fn dynamic_array_time_range_queries() {
+        let mut arr = dynamic_array::TimestampSaver::new_empty();
+        for ts in [100, 110, 110, 120, 200, 205, 260] {
+            arr.append(ts);
+        }
+        assert_eq!(arr.lower_bound(110), 1);
+        assert_eq!(arr.upper_bound(110), 3);
+        assert_eq!(arr.lower_bound(1_000), arr.len());
+        assert_eq!(arr.range(110, 205), &[110, 110, 120, 200]);
+        assert_eq!(arr.range(300, 200), &[] as &[u64]);
+        assert_eq!(arr.count_between(0, 101), 1);
+
+        assert_eq!(
+            arr.rate_per_interval(50),
+            vec![(100, 4), (150, 0), (200, 2), (250, 1)]
+        );
+        assert_eq!(arr.gaps(50), vec![(120, 200), (205, 260)]);
+        assert_eq!(arr.min_inter_arrival(), Some(0));
+        assert_eq!(arr.max_inter_arrival(), Some(80));
+
+        let empty = dynamic_array::TimestampSaver::new_empty();
+        assert_eq!(empty.rate_per_interval(10), vec![]);
+        assert_eq!(empty.max_inter_arrival(), None);
+    }
+
+    #[test]
+    fn dynamic_array_growth_policies() {
+        use dynamic_array::{Doubling, Exact, FixedChunk, TimestampSaver};
+
+        fn appends(mut arr: TimestampSaver) -> dynamic_array::AllocationStats {
+            for ts in 0..1_000 {
+                arr.append(ts);
+            }
+            assert_eq!(arr[999], 999);
+            arr.stats()
+        }
+        let doubling = appends(TimestampSaver::with_policy(Doubling));
+        let default = appends(TimestampSaver::new_empty());
+        let chunks = appends(TimestampSaver::with_policy(FixedChunk(100)));
+        let exact = appends(TimestampSaver::with_policy(Exact));
+
+        // 10, 20, ..., 1280
+        assert_eq!(doubling.reallocations, 8);
+        assert_eq!(doubling.wasted_bytes, 280 * 8);
+        assert!(default.reallocations > doubling.reallocations);
+        assert!(default.wasted_bytes < doubling.wasted_bytes);
+        assert_eq!(chunks.reallocations, 10);
+        assert_eq!(chunks.wasted_bytes, 0);
+        assert_eq!(exact.reallocations, 1_000);
+        assert_eq!(exact.wasted_bytes, 0);
+        assert!(exact.bytes_copied <= 8 * 1_000 * 1_000 / 2);
+
+        let mut arr = TimestampSaver::with_policy(FixedChunk(64));
+        arr.reserve(65);
+        assert_eq!(arr.capacity(), 128);
+        arr.shrink_to_fit();
+        assert_eq!(arr.stats().reallocations, 2);
+    }
+
+    #[test]
+    fn persistent_vector_snapshots_share_structure() {
+        // enough values for three levels of branches
+        let n = 40_000_u64;
+        let mut snapshots = vec![];
+        let mut vector = persistent_vector::TimestampSnapshot::new_empty();
+        for ts in 0..n {
+            if ts % 10_000 == 0 {
+                snapshots.push(vector.clone());
+            }
+            vector = vector.push(ts);
+        }
+        assert_eq!(vector.len(), n as usize);
+        assert!(vector.iter().copied().eq(0..n));
+        assert_eq!(vector.iter().len(), n as usize);
+        for i in [0, 31, 32, 33, 1_055, 1_056, 32_767, 32_768, 39_999] {
+            assert_eq!(vector.get(i), Some(&(i as u64)));
+        }
+        assert_eq!(vector.get(n as usize), None);
+
+        // older versions are unchanged
+        for (i, snapshot) in snapshots.iter().enumerate() {
+            assert_eq!(snapshot.len(), i * 10_000);
+            assert!(snapshot.iter().copied().eq(0..(i as u64 * 10_000)));
+        }
+
+        let changed = vector.set(5, 500).set(39_990, 7);
+        assert_eq!(
+            (changed.get(5), changed.get(39_990)),
+            (Some(&500), Some(&7))
+        );
+        assert_eq!(
+            (vector.get(5), vector.get(39_990)),
+            (Some(&5), Some(&39_990))
+        );
+        assert_eq!(snapshots[2].iter().nth(5), Some(&5));
+
+        let small: persistent_vector::PersistentVector<String> =
+            (0..3).map(|i| format!("ts {}", i)).collect();
+        assert_eq!(small.push("ts 3".to_string()).len(), 4);
+        assert_eq!(small.len(), 3);
+    }
+
+    #[test]
+    fn unrolled_list_matches_vec() {
+        let mut list = unrolled_list::TransactionLog::new_empty();
+        let mut model: Vec<u64> = vec![];
+        let mut rng = thread_rng();
+        for i in 0..5_000 {
+            match rng.gen_range(0, 6) {
+                0 | 1 => {
+                    list.append(i);
+                    model.push(i);
+                }
+                2 => {
+                    let at = rng.gen_range(0, model.len() as u64 + 1);
+                    list.insert(at, i);
+                    model.insert(at as usize, i);
+                }
+                3 if !model.is_empty() => {
+                    let at = rng.gen_range(0, model.len() as u64);
+                    assert_eq!(list.remove(at), Some(model.remove(at as usize)));
+                }
+                4 if !model.is_empty() => {
+                    assert_eq!(list.pop(), Some(model.remove(0)));
+                }
+                _ => assert_eq!(list.pop_back(), model.pop()),
+            }
+            assert_eq!(list.length, model.len() as u64);
+        }
+
+        assert!(list.node_count() <= model.len() / 32 + 2);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), model);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            model.iter().rev().copied().collect::<Vec<_>>()
+        );
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), model.first());
+        assert_eq!(iter.next_back(), model.last());
+        assert_eq!(iter.len(), model.len().saturating_sub(2));
+        for (i, value) in model.iter().enumerate() {
+            assert_eq!(list.get(i as u64), Some(value));
+        }
+        assert_eq!(list.get(model.len() as u64), None);
+        assert_eq!(list.remove(model.len() as u64), None);
+
+        if let Some(first) = list.get_mut(0) {
+            *first = 42;
+            model[0] = 42;
+        }
+        assert_eq!(list.into_iter().rev().collect::<Vec<_>>(), {
+            model.reverse();
+            model
+        });
+    }
+
     #[test]
     fn dynamic_array_append() {
         let mut arr = dynamic_array::TimestampSaver::new_empty(); 
@@ -47,7 +1342,7 @@ mod tests {
         for i in 0..max {
             arr.append(i as u64); 
         }
-        assert_eq!(arr.length, max);
+        assert_eq!(arr.len(), max);
     }
 
     #[test]
@@ -57,7 +1352,7 @@ mod tests {
         for i in 0..max {
             arr.append(i as u64); 
         }
-        assert_eq!(arr.length, max); 
+        assert_eq!(arr.len(), max); 
         for i in 0..max {
             assert_eq!(arr.at(i), Some(i as u64));
         }
@@ -70,7 +1365,7 @@ mod tests {
         for i in 0..5 {
             arr.append(i as u64); 
         }
-        assert_eq!(arr.length, 5); 
+        assert_eq!(arr.len(), 5); 
         // let mut iter = arr.into_iter(); 
         // for i in 0..5 {
         //     assert_eq!(iter.next(), Some(i as u64)); 
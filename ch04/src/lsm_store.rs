@@ -0,0 +1,546 @@
+use crate::durable_log::LogRecord;
+use crate::skip_list::SkipList;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+// ref
+// O'Neil et al., "The Log-Structured Merge-Tree (LSM-Tree)", and the LevelDB table format
+// https://github.com/google/leveldb/blob/main/doc/table_format.md
+//
+// Writes go to an in-memory skip list (the memtable). Once it holds `memtable_bytes` worth of keys
+// and values it is written out in key order as an immutable table file ("00000000000000000003.sst"):
+//
+//   | key length: u32 | value length: u32 | key | value | ... | index | first id: u64 | index offset: u64 | magic: u32 |
+//
+// A deleted key is stored as a tombstone with the value length u32::MAX. Every `index_interval`th
+// entry goes into the sparse index, `key length: u32 | key | entry offset: u64`, which is kept in
+// memory, so a lookup reads a single block of entries from each table it has to check.
+//
+// Tables only shadow older ones, so a read checks the memtable and then the tables newest first.
+// Once there are `compaction_trigger` tables, a background thread merges them into one, keeping the
+// newest value of every key. The merge always starts at the oldest table, so tombstones can go too.
+// The merged table takes the place of the newest input and records the id of the oldest one, so
+// inputs that survive a crash before they were deleted are recognised and removed on open.
+// Writes that are still in the memtable are lost on a crash, `flush` writes them out.
+
+const TABLE_EXTENSION: &str = "sst";
+const TEMP_EXTENSION: &str = "tmp";
+const MAGIC: u32 = 0x4c53_4d31;
+const FOOTER_SIZE: u64 = 20;
+const TOMBSTONE: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug)]
+pub struct StoreConfig {
+    // approximate size of the encoded keys and values in the memtable before it is flushed
+    pub memtable_bytes: usize,
+    // every n-th entry of a table goes into its sparse index
+    pub index_interval: usize,
+    // number of tables that starts a compaction
+    pub compaction_trigger: usize,
+}
+
+impl StoreConfig {
+    pub fn new() -> StoreConfig {
+        StoreConfig {
+            memtable_bytes: 1 << 20,
+            index_interval: 16,
+            compaction_trigger: 4,
+        }
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> StoreConfig {
+        StoreConfig::new()
+    }
+}
+
+struct Table<K> {
+    id: u64,
+    // the oldest table merged into this one, `id` itself unless it came out of a compaction
+    first_id: u64,
+    // (first key of the block, offset of the block), sorted by key
+    index: Vec<(K, u64)>,
+    // where the entries end and the index starts
+    data_end: u64,
+}
+
+struct Compaction<K> {
+    // the compacted tables are always the oldest ones, tables[..inputs]
+    inputs: usize,
+    handle: JoinHandle<io::Result<Table<K>>>,
+}
+
+// the bounds are on the struct because dropping the store flushes the memtable
+pub struct LsmStore<K, V>
+where
+    K: Ord + Clone + LogRecord + Send + 'static,
+    V: Clone + LogRecord + Send + 'static,
+{
+    dir: PathBuf,
+    config: StoreConfig,
+    // a None value is a tombstone, it has to shadow older values of the key in the tables
+    memtable: SkipList<K, Option<V>>,
+    memtable_size: usize,
+    // oldest first
+    tables: Vec<Table<K>>,
+    next_id: u64,
+    compaction: Option<Compaction<K>>,
+}
+
+impl<K, V> LsmStore<K, V>
+where
+    K: Ord + Clone + LogRecord + Send + 'static,
+    V: Clone + LogRecord + Send + 'static,
+{
+    pub fn open(dir: impl AsRef<Path>, config: StoreConfig) -> io::Result<LsmStore<K, V>> {
+        assert!(
+            config.index_interval > 0,
+            "index interval has to be at least 1"
+        );
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut ids = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            match path.extension().and_then(|e| e.to_str()) {
+                // left behind by a compaction that did not finish
+                Some(TEMP_EXTENSION) => fs::remove_file(&path)?,
+                Some(TABLE_EXTENSION) => {
+                    if let Some(id) = path
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .and_then(|s| s.parse::<u64>().ok())
+                    {
+                        ids.push(id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        ids.sort_unstable();
+
+        let mut tables: Vec<Table<K>> = vec![];
+        for id in ids {
+            let table = read_table(&dir, id)?;
+            // inputs of a compaction that were left behind after the merged table was in place
+            while tables.last().is_some_and(|last| last.id >= table.first_id) {
+                let leftover = tables.pop().expect("checked above");
+                fs::remove_file(table_path(&dir, leftover.id))?;
+            }
+            tables.push(table);
+        }
+        let next_id = tables.last().map_or(0, |table| table.id + 1);
+        Ok(LsmStore {
+            dir,
+            config,
+            memtable: SkipList::new_empty(16),
+            memtable_size: 0,
+            tables,
+            next_id,
+            compaction: None,
+        })
+    }
+
+    pub fn put(&mut self, key: K, value: V) -> io::Result<()> {
+        self.write(key, Some(value))
+    }
+
+    pub fn delete(&mut self, key: K) -> io::Result<()> {
+        self.write(key, None)
+    }
+
+    pub fn get(&self, key: &K) -> io::Result<Option<V>> {
+        if let Some(value) = self.memtable.get(key) {
+            return Ok(value.clone());
+        }
+        for table in self.tables.iter().rev() {
+            if let Some(value) = self.search_table(table, key)? {
+                return Ok(value);
+            }
+        }
+        Ok(None)
+    }
+
+    // writes the memtable out as a new table, even if it is not full yet
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.memtable.length == 0 {
+            return Ok(());
+        }
+        let id = self.next_id;
+        let table = write_table(
+            &self.dir,
+            id,
+            self.config.index_interval,
+            self.memtable
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), v.clone()))),
+        )?;
+        self.next_id += 1;
+        self.tables.push(table);
+        self.memtable = SkipList::new_empty(16);
+        self.memtable_size = 0;
+        self.start_compaction()
+    }
+
+    // merges all tables into one and waits for it, including a compaction that is already running
+    pub fn compact(&mut self) -> io::Result<()> {
+        self.finish_compaction(true)?;
+        if self.tables.len() > 1 {
+            self.spawn_compaction();
+            self.finish_compaction(true)?;
+        }
+        Ok(())
+    }
+
+    pub fn table_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    fn write(&mut self, key: K, value: Option<V>) -> io::Result<()> {
+        self.memtable_size += key.encode().len() + value.as_ref().map_or(0, |v| v.encode().len());
+        self.memtable.insert(key, value);
+        if self.memtable_size >= self.config.memtable_bytes {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // Some(None) is a tombstone, None means the table knows nothing about the key
+    fn search_table(&self, table: &Table<K>, key: &K) -> io::Result<Option<Option<V>>> {
+        // the last block that starts at or before the key
+        let block = table.index.partition_point(|(first, _)| first <= key);
+        if block == 0 {
+            return Ok(None);
+        }
+        let start = table.index[block - 1].1;
+        let end = table
+            .index
+            .get(block)
+            .map_or(table.data_end, |(_, offset)| *offset);
+
+        let mut file = File::open(table_path(&self.dir, table.id))?;
+        file.seek(SeekFrom::Start(start))?;
+        let mut reader = file.take(end - start);
+        while let Some((entry_key, value)) = read_entry::<_, K, V>(&mut reader)? {
+            if entry_key == *key {
+                return Ok(Some(value));
+            }
+            if entry_key > *key {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    fn start_compaction(&mut self) -> io::Result<()> {
+        self.finish_compaction(false)?;
+        if self.compaction.is_none() && self.tables.len() >= self.config.compaction_trigger.max(2) {
+            self.spawn_compaction();
+        }
+        Ok(())
+    }
+
+    fn spawn_compaction(&mut self) {
+        let dir = self.dir.clone();
+        let inputs: Vec<(u64, u64)> = self
+            .tables
+            .iter()
+            .map(|table| (table.id, table.data_end))
+            .collect();
+        let index_interval = self.config.index_interval;
+        let handle = thread::spawn(move || merge_tables::<K, V>(&dir, &inputs, index_interval));
+        self.compaction = Some(Compaction {
+            inputs: self.tables.len(),
+            handle,
+        });
+    }
+
+    // swaps the merged table in for its inputs once the compaction is done
+    fn finish_compaction(&mut self, wait: bool) -> io::Result<()> {
+        match self.compaction {
+            Some(ref compaction) if wait || compaction.handle.is_finished() => {}
+            _ => return Ok(()),
+        }
+        let compaction = self.compaction.take().expect("checked above");
+        let merged = compaction
+            .handle
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("compaction panicked")))?;
+
+        // the merged table replaces the newest input, renaming it over that file is atomic
+        fs::rename(
+            temp_path(&self.dir, merged.id),
+            table_path(&self.dir, merged.id),
+        )?;
+        // the file now holds the merged data, so the in-memory tables have to follow right away
+        let inputs: Vec<Table<K>> = self.tables.drain(..compaction.inputs - 1).collect();
+        self.tables[0] = merged;
+        // inputs that can't be removed now are removed by the next `open`, it sees the merged
+        // table covers them
+        for table in inputs {
+            let _ = fs::remove_file(table_path(&self.dir, table.id));
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> Drop for LsmStore<K, V>
+where
+    K: Ord + Clone + LogRecord + Send + 'static,
+    V: Clone + LogRecord + Send + 'static,
+{
+    // like BufWriter, errors are ignored here, call `flush` to see them
+    fn drop(&mut self) {
+        let _ = self.flush();
+        let _ = self.finish_compaction(true);
+    }
+}
+
+fn table_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", id, TABLE_EXTENSION))
+}
+
+fn temp_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", id, TEMP_EXTENSION))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut word = [0; 4];
+    reader.read_exact(&mut word)?;
+    Ok(u32::from_le_bytes(word))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut word = [0; 8];
+    reader.read_exact(&mut word)?;
+    Ok(u64::from_le_bytes(word))
+}
+
+fn read_bytes(reader: &mut impl Read, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn decode<T: LogRecord>(bytes: &[u8]) -> io::Result<T> {
+    T::decode(bytes).ok_or_else(|| invalid_data("undecodable key or value in table".to_string()))
+}
+
+// reads the next entry, or None at the end of the reader
+fn read_entry<R: Read, K: LogRecord, V: LogRecord>(
+    reader: &mut R,
+) -> io::Result<Option<(K, Option<V>)>> {
+    let key_length = match read_u32(reader) {
+        Ok(length) => length as usize,
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let value_length = read_u32(reader)?;
+    let key = decode(&read_bytes(reader, key_length)?)?;
+    let value = match value_length {
+        TOMBSTONE => None,
+        length => Some(decode(&read_bytes(reader, length as usize)?)?),
+    };
+    Ok(Some((key, value)))
+}
+
+// loads the sparse index of a table
+fn read_table<K: LogRecord>(dir: &Path, id: u64) -> io::Result<Table<K>> {
+    let path = table_path(dir, id);
+    let mut file = File::open(&path)?;
+    let size = file.metadata()?.len();
+    if size < FOOTER_SIZE {
+        return Err(invalid_data(format!("{} is too short", path.display())));
+    }
+    file.seek(SeekFrom::Start(size - FOOTER_SIZE))?;
+    let first_id = read_u64(&mut file)?;
+    let data_end = read_u64(&mut file)?;
+    if read_u32(&mut file)? != MAGIC || data_end > size - FOOTER_SIZE || first_id > id {
+        return Err(invalid_data(format!("{} is not a table", path.display())));
+    }
+
+    file.seek(SeekFrom::Start(data_end))?;
+    let mut reader = BufReader::new(file.take(size - FOOTER_SIZE - data_end));
+    let mut index = vec![];
+    loop {
+        let key_length = match read_u32(&mut reader) {
+            Ok(length) => length as usize,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        let key = decode(&read_bytes(&mut reader, key_length)?)?;
+        index.push((key, read_u64(&mut reader)?));
+    }
+    Ok(Table {
+        id,
+        first_id,
+        index,
+        data_end,
+    })
+}
+
+// writes the entries, which have to be in key order, to a temporary file and renames it into place
+fn write_table<K, V>(
+    dir: &Path,
+    id: u64,
+    index_interval: usize,
+    entries: impl Iterator<Item = io::Result<(K, Option<V>)>>,
+) -> io::Result<Table<K>>
+where
+    K: LogRecord,
+    V: LogRecord,
+{
+    let table = write_temp_table(dir, id, id, index_interval, entries)?;
+    fs::rename(temp_path(dir, id), table_path(dir, id))?;
+    Ok(table)
+}
+
+fn write_temp_table<K, V>(
+    dir: &Path,
+    id: u64,
+    first_id: u64,
+    index_interval: usize,
+    entries: impl Iterator<Item = io::Result<(K, Option<V>)>>,
+) -> io::Result<Table<K>>
+where
+    K: LogRecord,
+    V: LogRecord,
+{
+    let file = File::create(temp_path(dir, id))?;
+    let mut writer = BufWriter::new(file);
+    let mut index = vec![];
+    let mut offset = 0;
+    for (i, entry) in entries.enumerate() {
+        let (key, value) = entry?;
+        let key_bytes = key.encode();
+        let value_bytes = value.as_ref().map(|v| v.encode());
+        if i % index_interval == 0 {
+            index.push((key, offset));
+        }
+        let value_length = value_bytes.as_ref().map_or(TOMBSTONE, |v| v.len() as u32);
+        writer.write_all(&(key_bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&value_length.to_le_bytes())?;
+        writer.write_all(&key_bytes)?;
+        offset += 8 + key_bytes.len() as u64;
+        if let Some(value_bytes) = value_bytes {
+            writer.write_all(&value_bytes)?;
+            offset += value_bytes.len() as u64;
+        }
+    }
+    let data_end = offset;
+    for (key, offset) in &index {
+        let key_bytes = key.encode();
+        writer.write_all(&(key_bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&key_bytes)?;
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+    writer.write_all(&first_id.to_le_bytes())?;
+    writer.write_all(&data_end.to_le_bytes())?;
+    writer.write_all(&MAGIC.to_le_bytes())?;
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()?;
+    Ok(Table {
+        id,
+        first_id,
+        index,
+        data_end,
+    })
+}
+
+// all entries of a table in key order
+struct TableReader<K, V> {
+    reader: io::Take<BufReader<File>>,
+    peeked: Option<(K, Option<V>)>,
+}
+
+impl<K: LogRecord, V: LogRecord> TableReader<K, V> {
+    fn open(dir: &Path, id: u64, data_end: u64) -> io::Result<TableReader<K, V>> {
+        let mut reader = BufReader::new(File::open(table_path(dir, id))?).take(data_end);
+        let peeked = read_entry(&mut reader)?;
+        Ok(TableReader { reader, peeked })
+    }
+
+    fn advance(&mut self) -> io::Result<Option<(K, Option<V>)>> {
+        let next = read_entry(&mut self.reader)?;
+        Ok(std::mem::replace(&mut self.peeked, next))
+    }
+}
+
+// the newest entry of every key over all tables, in key order
+struct MergeIterator<K, V> {
+    // oldest first
+    readers: Vec<TableReader<K, V>>,
+}
+
+impl<K: Ord + LogRecord, V: LogRecord> MergeIterator<K, V> {
+    fn merge_next(&mut self) -> io::Result<Option<(K, Option<V>)>> {
+        // the smallest key of all tables, the newest table wins a tie
+        let mut newest: Option<usize> = None;
+        for (i, reader) in self.readers.iter().enumerate() {
+            if let Some((ref key, _)) = reader.peeked {
+                let smaller = match newest.and_then(|n| self.readers[n].peeked.as_ref()) {
+                    Some((smallest, _)) => key <= smallest,
+                    None => true,
+                };
+                if smaller {
+                    newest = Some(i);
+                }
+            }
+        }
+        let newest = match newest {
+            Some(newest) => newest,
+            None => return Ok(None),
+        };
+        let (key, value) = self.readers[newest].advance()?.expect("peeked above");
+        // skip the overwritten values in the older tables
+        for reader in &mut self.readers[..newest] {
+            if reader.peeked.as_ref().is_some_and(|(k, _)| *k == key) {
+                reader.advance()?;
+            }
+        }
+        Ok(Some((key, value)))
+    }
+}
+
+impl<K: Ord + LogRecord, V: LogRecord> Iterator for MergeIterator<K, V> {
+    type Item = io::Result<(K, Option<V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.merge_next() {
+                // the merge includes the oldest table, so there is nothing left for a tombstone to hide
+                Ok(Some((_, None))) => continue,
+                Ok(entry) => return entry.map(Ok),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+// merges the tables `inputs` (id and end of data, oldest first) into a temporary file for the newest
+fn merge_tables<K, V>(
+    dir: &Path,
+    inputs: &[(u64, u64)],
+    index_interval: usize,
+) -> io::Result<Table<K>>
+where
+    K: Ord + LogRecord,
+    V: LogRecord,
+{
+    let readers = inputs
+        .iter()
+        .map(|(id, data_end)| TableReader::<K, V>::open(dir, *id, *data_end))
+        .collect::<io::Result<Vec<_>>>()?;
+    let (first_id, id) = (inputs[0].0, inputs[inputs.len() - 1].0);
+    write_temp_table(dir, id, first_id, index_interval, MergeIterator { readers })
+}
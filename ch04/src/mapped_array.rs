@@ -0,0 +1,188 @@
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+use std::slice;
+
+// A TimestampSaver whose buffer is a memory mapped file, so reopening it maps the timestamps
+// straight back in instead of reading and parsing them. The file starts with a header:
+//
+//   | magic: "TSSAVER\0" | version: u64 | length: u64 | capacity: u64 | capacity * timestamp: u64 |
+//
+// Numbers are in native byte order, the header and the timestamps are used as they are in memory;
+// a file from a machine with the other byte order fails the version check. Growing extends the
+// file and maps it again. The kernel writes dirty pages back in any order, so the length in the
+// header lags behind: `append` only writes the timestamp, and `flush` (also run before growing and
+// on drop) first syncs the new timestamps and only then writes and syncs the length. A crash loses
+// the timestamps since the last flush, but the header never counts one that is not on disk.
+// Nothing else may change the file while it is mapped.
+
+const MAGIC: [u8; 8] = *b"TSSAVER\0";
+const VERSION: u64 = 1;
+const HEADER_SIZE: usize = 32;
+// the header is four u64 fields, the magic number is field 0
+const VERSION_FIELD: usize = 1;
+const LENGTH_FIELD: usize = 2;
+const CAPACITY_FIELD: usize = 3;
+const MIN_SIZE: usize = 10;
+
+pub struct MappedTimestampSaver {
+    file: File,
+    map: MmapMut,
+    length: usize,
+    // the length in the header, everything up to it is on disk
+    flushed: usize,
+    cap: usize,
+}
+
+impl MappedTimestampSaver {
+    // opens the saver in `path`, a missing or empty file becomes an empty saver
+    pub fn open(path: impl AsRef<Path>) -> io::Result<MappedTimestampSaver> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if file.metadata()?.len() == 0 {
+            file.set_len(file_size(MIN_SIZE))?;
+            let mut saver = MappedTimestampSaver {
+                map: unsafe { MmapMut::map_mut(&file)? },
+                file,
+                length: 0,
+                flushed: 0,
+                cap: MIN_SIZE,
+            };
+            saver.map[..8].copy_from_slice(&MAGIC);
+            saver.write_header_field(VERSION_FIELD, VERSION);
+            saver.write_header_field(LENGTH_FIELD, 0);
+            saver.write_header_field(CAPACITY_FIELD, MIN_SIZE as u64);
+            return Ok(saver);
+        }
+
+        let size = file.metadata()?.len();
+        if size < HEADER_SIZE as u64 {
+            return Err(invalid_data("file is too short for a header".to_string()));
+        }
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        if map[..8] != MAGIC {
+            return Err(invalid_data("not a timestamp file".to_string()));
+        }
+        let mut saver = MappedTimestampSaver {
+            map,
+            file,
+            length: 0,
+            flushed: 0,
+            cap: 0,
+        };
+        let version = saver.read_header_field(VERSION_FIELD);
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported version {}, expected {}",
+                version, VERSION
+            )));
+        }
+        let length = saver.read_header_field(LENGTH_FIELD);
+        let cap = saver.read_header_field(CAPACITY_FIELD);
+        if length > cap || cap > (size - HEADER_SIZE as u64) / 8 {
+            return Err(invalid_data(format!(
+                "header says length {} and capacity {}, but the file has {} bytes",
+                length, cap, size
+            )));
+        }
+        saver.length = length as usize;
+        saver.flushed = length as usize;
+        saver.cap = cap as usize;
+        Ok(saver)
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn append(&mut self, value: u64) -> io::Result<()> {
+        if self.length == self.cap {
+            self.grow(self.length + 1)?;
+        }
+        let at = HEADER_SIZE + self.length * 8;
+        self.map[at..at + 8].copy_from_slice(&value.to_ne_bytes());
+        self.length += 1;
+        Ok(())
+    }
+
+    pub fn at(&self, index: usize) -> Option<u64> {
+        self.get(index).copied()
+    }
+
+    // makes the timestamps appended since the last flush durable, and then counts them in the header
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.flushed == self.length {
+            return Ok(());
+        }
+        let from = HEADER_SIZE + self.flushed * 8;
+        self.map
+            .flush_range(from, (self.length - self.flushed) * 8)?;
+        self.write_header_field(LENGTH_FIELD, self.length as u64);
+        self.map.flush_range(0, HEADER_SIZE)?;
+        self.flushed = self.length;
+        Ok(())
+    }
+
+    fn grow(&mut self, min_cap: usize) -> io::Result<()> {
+        let new_cap = (self.cap + (self.cap >> 1)).max(min_cap).max(MIN_SIZE);
+        self.flush()?;
+        self.file.set_len(file_size(new_cap))?;
+        self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        self.cap = new_cap;
+        self.write_header_field(CAPACITY_FIELD, new_cap as u64);
+        Ok(())
+    }
+
+    fn read_header_field(&self, field: usize) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.map[field * 8..field * 8 + 8]);
+        u64::from_ne_bytes(bytes)
+    }
+
+    fn write_header_field(&mut self, field: usize, value: u64) {
+        self.map[field * 8..field * 8 + 8].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+impl Drop for MappedTimestampSaver {
+    // like BufWriter, errors are ignored here, call `flush` to see them
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Deref for MappedTimestampSaver {
+    type Target = [u64];
+
+    fn deref(&self) -> &[u64] {
+        // the map is page aligned and the header is a multiple of 8 bytes, so the data is aligned
+        unsafe {
+            slice::from_raw_parts(
+                self.map.as_ptr().add(HEADER_SIZE) as *const u64,
+                self.length,
+            )
+        }
+    }
+}
+
+fn file_size(cap: usize) -> u64 {
+    (HEADER_SIZE + cap * 8) as u64
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+// A persistent (immutable) singly linked log: nodes are never changed after they are created.
+// `append` puts a new node in front that points at the current one, so the new version shares
+// every older entry with the version it was made from. Snapshots are just another handle to a
+// node, cloning one is a single Arc increment, and since Arc is thread safe a snapshot can be
+// given to a reader on another thread while the writer keeps appending to its own version.
+type SharedLink<T> = Option<Arc<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    next: SharedLink<T>,
+}
+
+pub struct PersistentTransactionLog<T> {
+    // the most recent entry, `next` points back in time
+    head: SharedLink<T>,
+    pub length: u64,
+}
+
+impl<T> Clone for PersistentTransactionLog<T> {
+    fn clone(&self) -> Self {
+        PersistentTransactionLog {
+            head: self.head.clone(),
+            length: self.length,
+        }
+    }
+}
+
+impl<T> PersistentTransactionLog<T> {
+    pub fn new_empty() -> PersistentTransactionLog<T> {
+        PersistentTransactionLog {
+            head: None,
+            length: 0,
+        }
+    }
+
+    pub fn append(&self, value: T) -> PersistentTransactionLog<T> {
+        PersistentTransactionLog {
+            head: Some(Arc::new(Node {
+                value,
+                next: self.head.clone(),
+            })),
+            length: self.length + 1,
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    // the version before the most recent append
+    pub fn previous(&self) -> PersistentTransactionLog<T> {
+        match self.head {
+            Some(ref node) => PersistentTransactionLog {
+                head: node.next.clone(),
+                length: self.length - 1,
+            },
+            None => PersistentTransactionLog::new_empty(),
+        }
+    }
+
+    // true if both are the same version, not just equal entries
+    pub fn ptr_eq(&self, other: &PersistentTransactionLog<T>) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // newest entry first
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        ListIterator {
+            current: self.head.as_deref(),
+        }
+    }
+}
+
+impl<T> Drop for PersistentTransactionLog<T> {
+    // the default drop would recurse once per node, unlink the nodes nobody else shares in a loop
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+pub struct ListIterator<'a, T> {
+    current: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for ListIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.current.map(|node| {
+            self.current = node.next.as_deref();
+            &node.value
+        })
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PersistentTransactionLog<T> {
+    type Item = &'a T;
+    type IntoIter = ListIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
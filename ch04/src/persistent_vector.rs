@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+// ref
+// Hickey, Clojure's PersistentVector, as explained in
+// https://hypirion.com/musings/understanding-persistent-vector-pt-1
+//
+// A trie with 32 children per node. The bits of an index pick the path, five per level from the
+// top, and the leaves hold 32 values each. The last (up to) 32 values are kept in a separate tail,
+// so most pushes only copy the tail; once it is full it moves into the trie as a new leaf.
+// Nodes are never changed, `push` and `set` copy the path they touch (at most 32 * depth values
+// and links) and share everything else with the version they started from, so cloning a version
+// is just two Arc increments.
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+enum Node<T> {
+    Branch(Vec<Arc<Node<T>>>),
+    Leaf(Vec<T>),
+}
+
+pub struct PersistentVector<T> {
+    root: Arc<Node<T>>,
+    tail: Arc<Vec<T>>,
+    // bits to shift an index by to get the child of the root, BITS * (levels of branches)
+    shift: usize,
+    length: usize,
+}
+
+// point-in-time copies of a timestamp array
+pub type TimestampSnapshot = PersistentVector<u64>;
+
+impl<T> Clone for PersistentVector<T> {
+    fn clone(&self) -> Self {
+        PersistentVector {
+            root: self.root.clone(),
+            tail: self.tail.clone(),
+            shift: self.shift,
+            length: self.length,
+        }
+    }
+}
+
+impl<T: Clone> PersistentVector<T> {
+    pub fn new_empty() -> PersistentVector<T> {
+        PersistentVector {
+            root: Arc::new(Node::Branch(vec![])),
+            tail: Arc::new(vec![]),
+            shift: BITS,
+            length: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.length {
+            return None;
+        }
+        Some(&self.chunk_for(index)[index & MASK])
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.tail.last()
+    }
+
+    pub fn push(&self, value: T) -> PersistentVector<T> {
+        // room in the tail
+        if self.length - self.tail_offset() < WIDTH {
+            let mut tail = Vec::with_capacity(self.tail.len() + 1);
+            tail.extend_from_slice(&self.tail);
+            tail.push(value);
+            return PersistentVector {
+                root: self.root.clone(),
+                tail: Arc::new(tail),
+                shift: self.shift,
+                length: self.length + 1,
+            };
+        }
+
+        // the full tail becomes a leaf of the trie
+        let leaf = Arc::new(Node::Leaf(self.tail.to_vec()));
+        let (root, shift) = if (self.length >> BITS) > (1 << self.shift) {
+            // the trie is full, it gets a new root with the old one as its first child
+            let root = Node::Branch(vec![self.root.clone(), new_path(self.shift, leaf)]);
+            (root, self.shift + BITS)
+        } else {
+            (self.push_tail(self.shift, &self.root, leaf), self.shift)
+        };
+        PersistentVector {
+            root: Arc::new(root),
+            tail: Arc::new(vec![value]),
+            shift,
+            length: self.length + 1,
+        }
+    }
+
+    // a version with `value` at `index`, panics if `index >= len`
+    pub fn set(&self, index: usize, value: T) -> PersistentVector<T> {
+        assert!(
+            index < self.length,
+            "index {} is out of bounds for length {}",
+            index,
+            self.length
+        );
+        let mut version = self.clone();
+        if index >= self.tail_offset() {
+            let mut tail = self.tail.to_vec();
+            tail[index & MASK] = value;
+            version.tail = Arc::new(tail);
+        } else {
+            version.root = Arc::new(set_in(self.shift, &self.root, index, value));
+        }
+        version
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        ListIterator {
+            vector: self,
+            chunk: &[],
+            index: 0,
+        }
+    }
+
+    // index of the first value in the tail
+    fn tail_offset(&self) -> usize {
+        if self.length < WIDTH {
+            0
+        } else {
+            ((self.length - 1) >> BITS) << BITS
+        }
+    }
+
+    // the leaf or tail that holds `index`, which has to be < len
+    fn chunk_for(&self, index: usize) -> &[T] {
+        if index >= self.tail_offset() {
+            return &self.tail;
+        }
+        let mut node = &self.root;
+        let mut level = self.shift;
+        loop {
+            match **node {
+                Node::Branch(ref children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+                Node::Leaf(ref values) => return values,
+            }
+        }
+    }
+
+    // copies the path to the rightmost leaf and adds `leaf` after it
+    fn push_tail(&self, level: usize, parent: &Node<T>, leaf: Arc<Node<T>>) -> Node<T> {
+        let mut children = match *parent {
+            Node::Branch(ref children) => children.clone(),
+            Node::Leaf(_) => unreachable!("leaves only hang off the lowest branches"),
+        };
+        let child = ((self.length - 1) >> level) & MASK;
+        if level == BITS {
+            children.push(leaf);
+        } else if child < children.len() {
+            children[child] = Arc::new(self.push_tail(level - BITS, &children[child], leaf));
+        } else {
+            children.push(new_path(level - BITS, leaf));
+        }
+        Node::Branch(children)
+    }
+}
+
+// `node` with a chain of single child branches above it, down from `level`
+fn new_path<T>(level: usize, node: Arc<Node<T>>) -> Arc<Node<T>> {
+    if level == 0 {
+        node
+    } else {
+        Arc::new(Node::Branch(vec![new_path(level - BITS, node)]))
+    }
+}
+
+fn set_in<T: Clone>(level: usize, node: &Node<T>, index: usize, value: T) -> Node<T> {
+    match *node {
+        Node::Branch(ref children) => {
+            let mut children = children.clone();
+            let child = (index >> level) & MASK;
+            children[child] = Arc::new(set_in(level - BITS, &children[child], index, value));
+            Node::Branch(children)
+        }
+        Node::Leaf(ref values) => {
+            let mut values = values.clone();
+            values[index & MASK] = value;
+            Node::Leaf(values)
+        }
+    }
+}
+
+impl<T: Clone> Default for PersistentVector<T> {
+    fn default() -> PersistentVector<T> {
+        PersistentVector::new_empty()
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> PersistentVector<T> {
+        let mut vector = PersistentVector::new_empty();
+        for value in iter {
+            vector = vector.push(value);
+        }
+        vector
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a PersistentVector<T> {
+    type Item = &'a T;
+    type IntoIter = ListIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// walks a leaf at a time instead of going down from the root for every value
+pub struct ListIterator<'a, T> {
+    vector: &'a PersistentVector<T>,
+    chunk: &'a [T],
+    index: usize,
+}
+
+impl<'a, T: Clone> Iterator for ListIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.index >= self.vector.length {
+            return None;
+        }
+        if self.index & MASK == 0 {
+            self.chunk = self.vector.chunk_for(self.index);
+        }
+        let value = &self.chunk[self.index & MASK];
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vector.length - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: Clone> ExactSizeIterator for ListIterator<'a, T> {}
@@ -0,0 +1,334 @@
+use std::mem::MaybeUninit;
+use std::ops::{Index, IndexMut};
+use std::ptr;
+use std::slice;
+
+// A fixed capacity circular buffer: the values live in buf[head..] and wrap around to the start of
+// buf, the oldest one is at `head`. Once it is full, every push drops a value from the other end,
+// so a rolling window of the last `capacity` timestamps never allocates again.
+// Indexes are relative to the oldest value, 0 is the front and len() - 1 the back.
+
+pub struct RingBuffer<T> {
+    buf: Box<[MaybeUninit<T>]>,
+    head: usize,
+    length: usize,
+}
+
+// the last timestamps of a device
+pub type TimestampWindow = RingBuffer<u64>;
+
+impl<T> RingBuffer<T> {
+    pub fn new_bounded(capacity: usize) -> RingBuffer<T> {
+        assert!(capacity > 0, "capacity has to be at least 1");
+        RingBuffer {
+            buf: (0..capacity).map(|_| MaybeUninit::uninit()).collect(),
+            head: 0,
+            length: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.length == self.buf.len()
+    }
+
+    // adds a newest value, when full the oldest one is overwritten and returned
+    pub fn push_back(&mut self, value: T) -> Option<T> {
+        let dropped = if self.is_full() {
+            self.pop_front()
+        } else {
+            None
+        };
+        let slot = self.slot(self.length);
+        self.buf[slot].write(value);
+        self.length += 1;
+        dropped
+    }
+
+    pub fn append(&mut self, value: T) -> Option<T> {
+        self.push_back(value)
+    }
+
+    // adds an oldest value, when full the newest one is overwritten and returned
+    pub fn push_front(&mut self, value: T) -> Option<T> {
+        let dropped = if self.is_full() {
+            self.pop_back()
+        } else {
+            None
+        };
+        self.head = self.slot(self.buf.len() - 1);
+        self.buf[self.head].write(value);
+        self.length += 1;
+        dropped
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+        let value = unsafe { self.buf[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.length -= 1;
+        Some(value)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        let slot = self.slot(self.length);
+        Some(unsafe { self.buf[slot].assume_init_read() })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.length.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.length {
+            Some(unsafe { self.buf[self.slot(index)].assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.length {
+            let slot = self.slot(index);
+            Some(unsafe { self.buf[slot].assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        let (first, second) = self.as_mut_slices();
+        let (first, second) = (first as *mut [T], second as *mut [T]);
+        // forget the values first, so a panicking drop can't lead to a double drop
+        self.head = 0;
+        self.length = 0;
+        unsafe {
+            ptr::drop_in_place(first);
+            ptr::drop_in_place(second);
+        }
+    }
+
+    // the values in order, the second slice is the part that wrapped around
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (first, second) = self.ranges();
+        let buf = self.buf.as_ptr() as *const T;
+        unsafe {
+            (
+                slice::from_raw_parts(buf.add(first.0), first.1),
+                slice::from_raw_parts(buf, second),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (first, second) = self.ranges();
+        let buf = self.buf.as_mut_ptr() as *mut T;
+        // the two ranges never overlap
+        unsafe {
+            (
+                slice::from_raw_parts_mut(buf.add(first.0), first.1),
+                slice::from_raw_parts_mut(buf, second),
+            )
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (first, second) = self.as_slices();
+        Iter { first, second }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (first, second) = self.as_mut_slices();
+        IterMut { first, second }
+    }
+
+    // index in buf of the value `offset` places after the oldest one
+    fn slot(&self, offset: usize) -> usize {
+        (self.head + offset) % self.buf.len()
+    }
+
+    // (start, length) of the part up to the end of buf, and the length of the wrapped part
+    fn ranges(&self) -> ((usize, usize), usize) {
+        let to_end = self.buf.len() - self.head;
+        if self.length <= to_end {
+            ((self.head, self.length), 0)
+        } else {
+            ((self.head, to_end), self.length - to_end)
+        }
+    }
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Index<usize> for RingBuffer<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let length = self.length;
+        self.get(index)
+            .unwrap_or_else(|| panic!("index {} is out of bounds for length {}", index, length))
+    }
+}
+
+impl<T> IndexMut<usize> for RingBuffer<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let length = self.length;
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index {} is out of bounds for length {}", index, length))
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for RingBuffer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> IntoIterator for RingBuffer<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { ring: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RingBuffer<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut RingBuffer<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// oldest to newest, the front walks `first` and then `second`, the back the other way around
+pub struct Iter<'a, T> {
+    first: &'a [T],
+    second: &'a [T],
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.first.is_empty() {
+            std::mem::swap(&mut self.first, &mut self.second);
+        }
+        let (value, rest) = self.first.split_first()?;
+        self.first = rest;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.first.len() + self.second.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.second.is_empty() {
+            std::mem::swap(&mut self.first, &mut self.second);
+        }
+        let (value, rest) = self.second.split_last()?;
+        self.second = rest;
+        Some(value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+pub struct IterMut<'a, T> {
+    first: &'a mut [T],
+    second: &'a mut [T],
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.first.is_empty() {
+            std::mem::swap(&mut self.first, &mut self.second);
+        }
+        let (value, rest) = std::mem::take(&mut self.first).split_first_mut()?;
+        self.first = rest;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.first.len() + self.second.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.second.is_empty() {
+            std::mem::swap(&mut self.first, &mut self.second);
+        }
+        let (value, rest) = std::mem::take(&mut self.second).split_last_mut()?;
+        self.second = rest;
+        Some(value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+pub struct IntoIter<T> {
+    ring: RingBuffer<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.ring.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.ring.len(), Some(self.ring.len()))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.ring.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
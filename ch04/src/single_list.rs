@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::rc::Rc;
 
 // Rc is a pointer with shared ownership while RefCell provides interior mutability.
@@ -20,7 +21,7 @@ impl Node {
     }
 }
 
-struct TransactionLog {
+pub struct TransactionLog {
     head: SingleLink,
     tail: SingleLink,
     pub length: u64,
@@ -61,6 +62,168 @@ impl TransactionLog {
                 .value
         })
     }
+
+    pub fn reverse(&mut self) {
+        self.tail = self.head.clone();
+        let mut reversed: SingleLink = None;
+        let mut current = self.head.take();
+        while let Some(node) = current {
+            current = std::mem::replace(&mut node.borrow_mut().next, reversed);
+            reversed = Some(node);
+        }
+        self.head = reversed;
+    }
+
+    // stable merge sort, the nodes are relinked and never copied
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&String, &String) -> Ordering,
+    {
+        self.tail.take();
+        self.head = merge_sort(self.head.take(), self.length, &mut compare);
+        self.tail = last_node(&self.head);
+    }
+
+    pub fn sort_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: Ord,
+        F: FnMut(&String) -> K,
+    {
+        self.sort_by(|a, b| key(a).cmp(&key(b)))
+    }
+
+    // removes consecutive entries for which `same(entry, previous kept entry)` is true
+    pub fn dedup_by<F>(&mut self, mut same: F)
+    where
+        F: FnMut(&String, &String) -> bool,
+    {
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let mut node_ref = node.borrow_mut();
+            loop {
+                let next = match node_ref.next {
+                    Some(ref next) if same(&next.borrow().value, &node_ref.value) => next.clone(),
+                    _ => break,
+                };
+                node_ref.next = next.borrow_mut().next.take();
+                self.length -= 1;
+            }
+            current = node_ref.next.clone();
+            if current.is_none() {
+                drop(node_ref);
+                self.tail = Some(node);
+            }
+        }
+    }
+
+    // moves all entries of `other` to the end of this log in O(1)
+    pub fn append_list(&mut self, mut other: TransactionLog) {
+        let other_head = match other.head.take() {
+            Some(head) => head,
+            None => return,
+        };
+        match self.tail.take() {
+            Some(old) => old.borrow_mut().next = Some(other_head),
+            None => self.head = Some(other_head),
+        };
+        self.tail = other.tail.take();
+        self.length += other.length;
+        other.length = 0;
+    }
+
+    // keeps the first `at` entries and returns the rest, panics if `at` is greater than the length
+    pub fn split_off(&mut self, at: u64) -> TransactionLog {
+        assert!(at <= self.length, "split index out of bounds");
+        if at == 0 {
+            return std::mem::replace(self, TransactionLog::new_empty());
+        }
+        let mut last = self.head.clone().expect("at > 0, so the list is not empty");
+        for _ in 1..at {
+            let next = last.borrow().next.clone().expect("at <= length");
+            last = next;
+        }
+        let rest_head = last.borrow_mut().next.take();
+        let rest = match rest_head {
+            Some(head) => TransactionLog {
+                head: Some(head),
+                tail: self.tail.replace(last),
+                length: self.length - at,
+            },
+            None => TransactionLog::new_empty(),
+        };
+        self.length = at;
+        rest
+    }
+}
+
+fn merge_sort<F>(head: SingleLink, length: u64, compare: &mut F) -> SingleLink
+where
+    F: FnMut(&String, &String) -> Ordering,
+{
+    if length < 2 {
+        return head;
+    }
+    let mid = length / 2;
+    let mut last = head.clone().expect("length >= 2");
+    for _ in 1..mid {
+        let next = last.borrow().next.clone().expect("mid < length");
+        last = next;
+    }
+    let right = last.borrow_mut().next.take();
+    drop(last);
+    let left = merge_sort(head, mid, compare);
+    let right = merge_sort(right, length - mid, compare);
+    merge(left, right, compare)
+}
+
+fn merge<F>(mut left: SingleLink, mut right: SingleLink, compare: &mut F) -> SingleLink
+where
+    F: FnMut(&String, &String) -> Ordering,
+{
+    let mut head: SingleLink = None;
+    let mut tail: SingleLink = None;
+    loop {
+        let next = match (left.take(), right.take()) {
+            (Some(l), Some(r)) => {
+                // only take from the right when it is strictly smaller, that keeps the sort stable
+                if compare(&r.borrow().value, &l.borrow().value) == Ordering::Less {
+                    right = r.borrow_mut().next.take();
+                    left = Some(l);
+                    r
+                } else {
+                    left = l.borrow_mut().next.take();
+                    right = Some(r);
+                    l
+                }
+            }
+            // the rest of one side is already sorted and linked
+            (Some(rest), None) | (None, Some(rest)) => {
+                match tail {
+                    Some(ref tail) => tail.borrow_mut().next = Some(rest),
+                    None => head = Some(rest),
+                };
+                break;
+            }
+            (None, None) => break,
+        };
+        match tail.take() {
+            Some(old) => old.borrow_mut().next = Some(next.clone()),
+            None => head = Some(next.clone()),
+        };
+        tail = Some(next);
+    }
+    head
+}
+
+fn last_node(head: &SingleLink) -> SingleLink {
+    let mut current = head.clone()?;
+    loop {
+        let next = current.borrow().next.clone();
+        match next {
+            Some(next) => current = next,
+            None => return Some(current),
+        }
+    }
 }
 
 fn main() {
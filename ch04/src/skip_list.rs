@@ -1,180 +1,469 @@
-use std::cell::{Ref, RefCell};
-use std::rc::Rc;
+use rand::rngs::SmallRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use std::ops::{Bound, RangeBounds};
 
 // ref
 // 花花酱 LeetCode 1206. Design Skiplist - 刷题找工作 EP367
 // https://youtu.be/783qX31AN08
 
-type Link = Option<Rc<RefCell<Node>>>;
+// Nodes are kept in a Vec and linked by their index, removed nodes leave a free slot that the
+// next insert reuses. The head is not a node, it only holds the first link of every level.
+//
+// Every link also knows its width, the number of base level steps it skips. Counting the head as
+// position 0 and the end of the list as position length + 1, a link from position a to position b
+// has width b - a. Adding up the widths on the way down gives the position of any node, which is
+// what `nth`, `rank` and `remove_at` use.
+type Link = Option<usize>;
 
-struct Node {
+#[derive(Clone)]
+struct Node<K, V> {
     // have different levels, so use vec
     next: Vec<Link>,
-    pub offset: u64,
-    pub command: String,
+    width: Vec<usize>,
+    key: K,
+    value: V,
 }
 
-impl Node {
-    fn new(links: Vec<Link>, offset: u64, command: String) -> Rc<RefCell<Node>> {
-        Rc::new(RefCell::new(Node {
-            next: links,
-            offset: offset,
-            command: command,
-        }))
-    }
-}
-
-struct SkipList {
-    head: Link,
-    // every level has a tail, so use vec
-    tails: Vec<Link>,
+#[derive(Clone)]
+pub struct SkipList<K, V> {
+    head: Vec<Link>,
+    head_width: Vec<usize>,
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
     max_level: usize,
+    // chance that a node is promoted to the next level
+    promotion: f64,
+    rng: SmallRng,
     pub length: u64,
 }
 
-#[derive(Clone)]
-pub struct BestTransactionLog {
-    head: Link,
-    tails: Vec<Link>,
-    max_level: usize,
-    pub length: u64,
+// how many nodes reach each level, index 0 is the base level that holds every node
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelStats {
+    pub nodes_per_level: Vec<u64>,
+    // total number of forward links, i.e. the memory spent on express lanes plus the base level
+    pub links: u64,
 }
 
-impl BestTransactionLog {
-    pub fn new_empty(max_level: usize) -> BestTransactionLog {
-        BestTransactionLog {
-            max_level: max_level,
-            head: None,
-            tails: vec![None; max_level + 1],
+// the skip list log maps offsets to commands
+pub type BestTransactionLog = SkipList<u64, String>;
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn new_empty(max_level: usize) -> SkipList<K, V> {
+        let rng = SmallRng::from_rng(thread_rng()).expect("thread_rng never fails");
+        SkipList::with_rng(max_level, 0.5, rng)
+    }
+
+    // the same seed and inserts always build the same levels
+    pub fn new_seeded(max_level: usize, promotion: f64, seed: u64) -> SkipList<K, V> {
+        SkipList::with_rng(max_level, promotion, SmallRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(max_level: usize, promotion: f64, rng: SmallRng) -> SkipList<K, V> {
+        assert!(
+            promotion > 0.0 && promotion < 1.0,
+            "promotion probability has to be between 0 and 1"
+        );
+        SkipList {
+            head: vec![None; max_level + 1],
+            head_width: vec![1; max_level + 1],
+            nodes: vec![],
+            free: vec![],
+            max_level,
+            promotion,
+            rng,
             length: 0,
         }
     }
 
-    pub fn append(&mut self, offset: u64, value: String) {
-        let level = 1 + if self.head.is_none() {
-            // use max level for first node
-            self.max_level
-        } else {
-            // use coin flips
-            self.get_level()
-        };
+    pub fn level_stats(&self) -> LevelStats {
+        let mut nodes_per_level = vec![0; self.max_level + 1];
+        let mut links = 0;
+        for node in self.nodes.iter().flatten() {
+            for count in nodes_per_level.iter_mut().take(node.next.len()) {
+                *count += 1;
+            }
+            links += node.next.len() as u64;
+        }
+        LevelStats {
+            nodes_per_level,
+            links,
+        }
+    }
 
-        let new = Node::new(vec![None; level], offset, value);
+    // inserts at the right position for `key`, an existing value for the key is replaced and returned
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let update = self.predecessors(&key);
+        if let Some(found) = self.next_of(update[0].0, 0) {
+            let node = self.node_mut(found);
+            if node.key == key {
+                return Some(std::mem::replace(&mut node.value, value));
+            }
+        }
 
-        // update the tails for each level
+        let level = 1 + self.get_level();
+        let position = update[0].1 + 1;
+        let mut next = vec![None; level];
+        let mut width = vec![0; level];
         for i in 0..level {
-            if let Some(old) = self.tails[i].take() {
-                let next = &mut old.borrow_mut().next;
-                next[i] = Some(new.clone());
+            let (prev, prev_position) = update[i];
+            next[i] = self.next_of(prev, i);
+            // the old link ended at prev_position + old width, which is one further back now
+            width[i] = prev_position + self.width_of(prev, i) + 1 - position;
+        }
+        let new = self.alloc(Node {
+            next,
+            width,
+            key,
+            value,
+        });
+        for (i, (prev, prev_position)) in update.iter().enumerate() {
+            if i < level {
+                self.set_next(*prev, i, Some(new));
+                *self.width_mut(*prev, i) = position - prev_position;
+            } else {
+                *self.width_mut(*prev, i) += 1;
             }
-            self.tails[i] = Some(new.clone());
         }
+        self.length += 1;
+        None
+    }
+
+    pub fn append(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
 
-        // first node in list
-        if self.head.is_none() {
-            self.head = Some(new.clone());
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let update = self.predecessors(key);
+        let found = self.next_of(update[0].0, 0)?;
+        if self.node(found).key != *key {
+            return None;
         }
-        self.length += 1;
+        Some(self.unlink(&update, found).value)
+    }
+
+    // the entry at position `index` in key order, starting at 0
+    pub fn nth(&self, index: usize) -> Option<(&K, &V)> {
+        let update = self.predecessors_at(index)?;
+        self.next_of(update[0].0, 0).map(|n| self.entry(n))
+    }
+
+    // the number of entries with a key smaller than `key`
+    pub fn rank(&self, key: &K) -> usize {
+        self.predecessors(key)[0].1
+    }
+
+    pub fn remove_at(&mut self, index: usize) -> Option<(K, V)> {
+        let update = self.predecessors_at(index)?;
+        let found = self.next_of(update[0].0, 0)?;
+        let node = self.unlink(&update, found);
+        Some((node.key, node.value))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find_node(key).map(|n| &self.node(n).value)
     }
 
-    fn get_level(&self) -> usize {
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.find_node(key)
+            .map(move |n| &mut self.node_mut(n).value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find_node(key).is_some()
+    }
+
+    pub fn find(&self, key: K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.get(&key).cloned()
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, K, V> {
+        self.iter_level(0)
+    }
+
+    // all entries with a key inside `range`, in order
+    pub fn range<R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Clone,
+        R: RangeBounds<K>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.ceiling_node(key),
+            Bound::Excluded(key) => {
+                let ceiling = self.ceiling_node(key);
+                match ceiling {
+                    Some(n) if self.node(n).key == *key => self.node(n).next[0],
+                    _ => ceiling,
+                }
+            }
+            Bound::Unbounded => self.head[0],
+        };
+        Range {
+            list: self,
+            current: start,
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    // the entry with the largest key that is less than or equal to `key`
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        let prev = self.predecessors(key)[0].0;
+        match self.next_of(prev, 0) {
+            Some(n) if self.node(n).key == *key => Some(self.entry(n)),
+            _ => prev.map(|n| self.entry(n)),
+        }
+    }
+
+    // the entry with the smallest key that is greater than or equal to `key`
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)> {
+        self.ceiling_node(key).map(|n| self.entry(n))
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.head[0].map(|n| self.entry(n))
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        // take the express lanes as far as they go
+        let mut current = None;
+        for level in (0..=self.max_level).rev() {
+            while let Some(next) = self.next_of(current, level) {
+                current = Some(next);
+            }
+        }
+        current.map(|n| self.entry(n))
+    }
+
+    fn iter_level(&self, level: usize) -> ListIterator<'_, K, V> {
+        ListIterator {
+            list: self,
+            current: self.head[level],
+            level,
+        }
+    }
+
+    fn get_level(&mut self) -> usize {
         let mut n = 0;
-        // p(true) = 0.5
-        while rand::random::<bool>() && n < self.max_level {
+        // p(true) = promotion
+        while n < self.max_level && self.rng.gen_bool(self.promotion) {
             n += 1;
         }
         n
     }
 
-    pub fn find(&self, offset: u64) -> Option<String> {
-        match self.head {
-            Some(ref head) => {
-                let mut start_level = self.max_level;
-                let node = head.clone();
-                let mut result = None;
-                loop {
-                    if node.borrow().next[start_level].is_some() {
-                        break;
-                    }
-                    start_level -= 1;
-                }
-                let mut n = node;
-                for level in (0..=start_level).rev() {
-                    loop {
-                        let next = n.clone();
-                        match next.borrow().next[level] {
-                            Some(ref next) if next.borrow().offset <= offset => n = next.clone(),
-                            _ => break,
-                        };
-                    }
-                    if n.borrow().offset == offset {
-                        let tmp = n.borrow();
-                        result = Some(tmp.command.clone());
-                        break;
-                    }
+    fn ceiling_node(&self, key: &K) -> Link {
+        let prev = self.predecessors(key)[0].0;
+        self.next_of(prev, 0)
+    }
+
+    fn find_node(&self, key: &K) -> Link {
+        let prev = self.predecessors(key)[0].0;
+        self.next_of(prev, 0).filter(|n| self.node(*n).key == *key)
+    }
+
+    // the last node with a smaller key on every level and its position, None stands for the head
+    fn predecessors(&self, key: &K) -> Vec<(Link, usize)> {
+        let mut update = vec![(None, 0); self.max_level + 1];
+        let mut current = None;
+        let mut position = 0;
+        for level in (0..=self.max_level).rev() {
+            while let Some(next) = self.next_of(current, level) {
+                if self.node(next).key < *key {
+                    position += self.width_of(current, level);
+                    current = Some(next);
+                } else {
+                    break;
                 }
-                result
             }
-            None => None,
+            update[level] = (current, position);
+        }
+        update
+    }
+
+    // like `predecessors`, but for the node at `index`
+    fn predecessors_at(&self, index: usize) -> Option<Vec<(Link, usize)>> {
+        if index as u64 >= self.length {
+            return None;
+        }
+        let mut update = vec![(None, 0); self.max_level + 1];
+        let mut current = None;
+        let mut position = 0;
+        for level in (0..=self.max_level).rev() {
+            // stop right before position index + 1
+            while position + self.width_of(current, level) <= index {
+                position += self.width_of(current, level);
+                current = self.next_of(current, level);
+            }
+            update[level] = (current, position);
+        }
+        Some(update)
+    }
+
+    fn unlink(&mut self, update: &[(Link, usize)], found: usize) -> Node<K, V> {
+        let node = self.nodes[found]
+            .take()
+            .expect("linked node is in a free slot");
+        for (i, (prev, _)) in update.iter().enumerate() {
+            if i < node.next.len() {
+                self.set_next(*prev, i, node.next[i]);
+                *self.width_mut(*prev, i) += node.width[i] - 1;
+            } else {
+                *self.width_mut(*prev, i) -= 1;
+            }
+        }
+        self.free.push(found);
+        self.length -= 1;
+        node
+    }
+
+    fn set_next(&mut self, at: Link, level: usize, next: Link) {
+        match at {
+            Some(n) => self.node_mut(n).next[level] = next,
+            None => self.head[level] = next,
+        }
+    }
+
+    fn width_mut(&mut self, at: Link, level: usize) -> &mut usize {
+        match at {
+            Some(n) => &mut self.node_mut(n).width[level],
+            None => &mut self.head_width[level],
         }
     }
 
-    fn iter_level(&self, level: usize) -> ListIterator {
-        ListIterator::new(self.head.clone(), level)
+    fn alloc(&mut self, node: Node<K, V>) -> usize {
+        match self.free.pop() {
+            Some(n) => {
+                self.nodes[n] = Some(node);
+                n
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
     }
 }
 
-impl IntoIterator for BestTransactionLog {
-    type Item = (u64, String);
-    type IntoIter = ListIterator;
+impl<K, V> SkipList<K, V> {
+    fn next_of(&self, at: Link, level: usize) -> Link {
+        match at {
+            Some(n) => self.node(n).next[level],
+            None => self.head[level],
+        }
+    }
+
+    fn width_of(&self, at: Link, level: usize) -> usize {
+        match at {
+            Some(n) => self.node(n).width[level],
+            None => self.head_width[level],
+        }
+    }
+
+    fn entry(&self, n: usize) -> (&K, &V) {
+        let node = self.node(n);
+        (&node.key, &node.value)
+    }
+
+    fn node(&self, n: usize) -> &Node<K, V> {
+        self.nodes[n]
+            .as_ref()
+            .expect("linked node is in a free slot")
+    }
+
+    fn node_mut(&mut self, n: usize) -> &mut Node<K, V> {
+        self.nodes[n]
+            .as_mut()
+            .expect("linked node is in a free slot")
+    }
+}
+
+impl<K, V> IntoIterator for SkipList<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
-        ListIterator::new(self.head, 0)
+        IntoIter {
+            current: self.head[0],
+            list: self,
+        }
     }
 }
 
-pub struct ListIterator {
-    current: Option<Rc<RefCell<Node>>>,
+pub struct ListIterator<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    current: Link,
     level: usize,
 }
 
-impl ListIterator {
-    fn new(start_at: Option<Rc<RefCell<Node>>>, level: usize) -> ListIterator {
-        ListIterator {
-            current: start_at,
-            level: level,
-        }
+impl<'a, K, V> Iterator for ListIterator<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        self.current.map(|n| {
+            let node = self.list.node(n);
+            self.current = node.next[self.level];
+            (&node.key, &node.value)
+        })
     }
 }
 
-impl Iterator for ListIterator {
-    type Item = (u64, String);
+pub struct Range<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    current: Link,
+    end: Bound<K>,
+}
 
-    fn next(&mut self) -> Option<(u64, String)> {
-        let current = &self.current;
-        let mut result = None;
-        self.current = match current {
-            Some(ref current) => {
-                let current = current.borrow();
-                result = Some((current.offset, current.command.clone()));
-                current.next[self.level].clone()
-            }
-            _ => None,
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = self.list.node(self.current?);
+        let in_range = match self.end {
+            Bound::Included(ref end) => node.key <= *end,
+            Bound::Excluded(ref end) => node.key < *end,
+            Bound::Unbounded => true,
         };
-        result
+        if !in_range {
+            self.current = None;
+            return None;
+        }
+        self.current = node.next[0];
+        Some((&node.key, &node.value))
     }
 }
 
-impl std::fmt::Debug for BestTransactionLog {
+pub struct IntoIter<K, V> {
+    list: SkipList<K, V>,
+    current: Link,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.current.map(|n| {
+            let node = self.list.nodes[n]
+                .take()
+                .expect("linked node is in a free slot");
+            self.current = node.next[0];
+            (node.key, node.value)
+        })
+    }
+}
+
+impl<K: Ord + std::fmt::Debug, V> std::fmt::Debug for SkipList<K, V> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self.head {
-            Some(ref _head) => {
+        match self.head[0] {
+            Some(_) => {
                 for level in (0..=self.max_level).rev() {
-                    let _ = write!(f, "{}: ", level);
+                    write!(f, "{}: ", level)?;
                     for n in self.iter_level(level) {
-                        let _ = write!(f, "[{}] ", n.0);
+                        write!(f, "[{:?}] ", n.0)?;
                     }
-                    let _ = writeln!(f, "");
+                    writeln!(f)?;
                 }
                 Ok(())
             }
@@ -182,3 +471,105 @@ impl std::fmt::Debug for BestTransactionLog {
         }
     }
 }
+
+impl<K: std::fmt::Debug, V> SkipList<K, V> {
+    // Graphviz source (render with e.g. `dot -Tsvg`): every node is drawn as a tower of its levels,
+    // left to right in key order, and every link is an edge labelled with its width.
+    // Towers are named by position, the head is position 0 and `nil` the end of the list.
+    pub fn to_dot(&self) -> String {
+        use std::fmt::Write;
+
+        // only the levels that are in use, an empty top level would just be a row of empty cells
+        let height = self
+            .nodes
+            .iter()
+            .flatten()
+            .map(|node| node.next.len())
+            .max()
+            .unwrap_or(1);
+        let mut positions = vec![0; self.nodes.len()];
+        let mut current = self.head[0];
+        let mut position = 0;
+        while let Some(n) = current {
+            position += 1;
+            positions[n] = position;
+            current = self.node(n).next[0];
+        }
+        let name = |link: Link| match link {
+            Some(n) => format!("n{}", positions[n]),
+            None => "nil".to_string(),
+        };
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph skip_list {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=plaintext];").unwrap();
+        let head_cells: Vec<String> = (0..height).map(|level| level.to_string()).collect();
+        writeln!(dot, "    head [label=<{}>];", tower(&head_cells, height)).unwrap();
+        let mut current = self.head[0];
+        while let Some(n) = current {
+            let node = self.node(n);
+            let mut cells = vec![String::new(); node.next.len()];
+            cells[0] = escape_html(&format!("{:?}", node.key));
+            writeln!(
+                dot,
+                "    {} [label=<{}>];",
+                name(current),
+                tower(&cells, height)
+            )
+            .unwrap();
+            current = node.next[0];
+        }
+        let nil_cells = vec!["nil".to_string(); height];
+        writeln!(dot, "    nil [label=<{}>];", tower(&nil_cells, height)).unwrap();
+
+        for level in (0..height).rev() {
+            let mut from = None;
+            loop {
+                let to = self.next_of(from, level);
+                let from_name = match from {
+                    Some(_) => name(from),
+                    None => "head".to_string(),
+                };
+                writeln!(
+                    dot,
+                    "    {}:l{}:e -> {}:l{}:w [label=\"{}\"];",
+                    from_name,
+                    level,
+                    name(to),
+                    level,
+                    self.width_of(from, level)
+                )
+                .unwrap();
+                if to.is_none() {
+                    break;
+                }
+                from = to;
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// an HTML-like label with one row per level, the top level first; missing levels are left blank
+fn tower(cells: &[String], height: usize) -> String {
+    let mut rows = String::new();
+    for level in (0..height).rev() {
+        match cells.get(level) {
+            Some(cell) => rows.push_str(&format!("<tr><td port=\"l{}\">{}</td></tr>", level, cell)),
+            None => rows.push_str("<tr><td border=\"0\"></td></tr>"),
+        }
+    }
+    format!(
+        "<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">{}</table>",
+        rows
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,327 @@
+// An unrolled linked list: a doubly linked list of chunks that hold up to NODE_CAPACITY values each.
+// Walking the list touches one node per chunk instead of one per value, and the values of a chunk
+// sit next to each other in memory. Like the arena list, the nodes live in a Vec and link to
+// each other by index, without Rc or RefCell. The values of a chunk are a VecDeque, so taking the
+// oldest value off the front is as cheap as taking the newest off the back.
+//
+// A full chunk is split in half to make room for an insert. A chunk that drops below half full
+// after a remove is merged with the next one if they fit together, or takes values over from it
+// otherwise, so apart from the first and the last chunk every chunk stays at least half full.
+
+use std::collections::VecDeque;
+
+const NODE_CAPACITY: usize = 64;
+
+struct Node<T> {
+    values: VecDeque<T>,
+    next: Option<usize>,
+    prev: Option<usize>,
+}
+
+pub struct TransactionLog<T> {
+    nodes: Vec<Option<Node<T>>>,
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    pub length: u64,
+}
+
+impl<T> TransactionLog<T> {
+    pub fn new_empty() -> TransactionLog<T> {
+        TransactionLog {
+            nodes: vec![],
+            free: vec![],
+            head: None,
+            tail: None,
+            length: 0,
+        }
+    }
+
+    pub fn append(&mut self, value: T) {
+        let tail = match self.tail {
+            Some(tail) if self.node(tail).values.len() < NODE_CAPACITY => tail,
+            _ => self.insert_node_after(self.tail, VecDeque::with_capacity(NODE_CAPACITY)),
+        };
+        self.node_mut(tail).values.push_back(value);
+        self.length += 1;
+    }
+
+    // removes the oldest value
+    pub fn pop(&mut self) -> Option<T> {
+        let head = self.head?;
+        let value = self.node_mut(head).values.pop_front();
+        if self.node(head).values.is_empty() {
+            self.unlink(head);
+        }
+        self.length -= 1;
+        value
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        let value = self.node_mut(tail).values.pop_back();
+        if self.node(tail).values.is_empty() {
+            self.unlink(tail);
+        }
+        self.length -= 1;
+        value
+    }
+
+    pub fn get(&self, index: u64) -> Option<&T> {
+        let (n, i) = self.locate(index)?;
+        Some(&self.node(n).values[i])
+    }
+
+    pub fn get_mut(&mut self, index: u64) -> Option<&mut T> {
+        let (n, i) = self.locate(index)?;
+        Some(&mut self.node_mut(n).values[i])
+    }
+
+    // puts `value` at `index` and moves everything after it back by one, panics if `index > length`
+    pub fn insert(&mut self, index: u64, value: T) {
+        assert!(
+            index <= self.length,
+            "insertion index {} is out of bounds for length {}",
+            index,
+            self.length
+        );
+        if index == self.length {
+            self.append(value);
+            return;
+        }
+        let (mut n, mut i) = self.locate(index).expect("index is in bounds");
+        if self.node(n).values.len() == NODE_CAPACITY {
+            // split, the upper half goes to a new node right after this one
+            let upper = self.node_mut(n).values.split_off(NODE_CAPACITY / 2);
+            let new = self.insert_node_after(Some(n), upper);
+            if i > NODE_CAPACITY / 2 {
+                n = new;
+                i -= NODE_CAPACITY / 2;
+            }
+        }
+        self.node_mut(n).values.insert(i, value);
+        self.length += 1;
+    }
+
+    pub fn remove(&mut self, index: u64) -> Option<T> {
+        let (n, i) = self.locate(index)?;
+        let value = self.node_mut(n).values.remove(i);
+        self.length -= 1;
+        self.rebalance(n);
+        value
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn iter(&self) -> ListIterator<'_, T> {
+        ListIterator {
+            list: self,
+            front: self.head,
+            front_index: 0,
+            back: self.tail,
+            back_index: self.tail.map_or(0, |n| self.node(n).values.len()),
+            remaining: self.length,
+        }
+    }
+
+    // the node that holds `index` and the position in it
+    fn locate(&self, index: u64) -> Option<(usize, usize)> {
+        if index >= self.length {
+            return None;
+        }
+        let mut index = index as usize;
+        let mut current = self.head;
+        while let Some(n) = current {
+            let node = self.node(n);
+            if index < node.values.len() {
+                return Some((n, index));
+            }
+            index -= node.values.len();
+            current = node.next;
+        }
+        None
+    }
+
+    // keeps `n` at least half full, or drops it if it ran empty
+    fn rebalance(&mut self, n: usize) {
+        let len = self.node(n).values.len();
+        if len == 0 {
+            self.unlink(n);
+            return;
+        }
+        if len >= NODE_CAPACITY / 2 {
+            return;
+        }
+        let next = match self.node(n).next {
+            Some(next) => next,
+            None => return,
+        };
+        let next_len = self.node(next).values.len();
+        let moved: VecDeque<T> = if len + next_len <= NODE_CAPACITY {
+            let values = std::mem::take(&mut self.node_mut(next).values);
+            self.unlink(next);
+            values
+        } else {
+            // the next node has more than enough, it stays at least half full
+            self.node_mut(next)
+                .values
+                .drain(..NODE_CAPACITY / 2 - len)
+                .collect()
+        };
+        self.node_mut(n).values.extend(moved);
+    }
+
+    fn insert_node_after(&mut self, prev: Option<usize>, values: VecDeque<T>) -> usize {
+        let next = match prev {
+            Some(p) => self.node(p).next,
+            None => self.head,
+        };
+        let node = Node { values, next, prev };
+        let n = match self.free.pop() {
+            Some(n) => {
+                self.nodes[n] = Some(node);
+                n
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        match prev {
+            Some(p) => self.node_mut(p).next = Some(n),
+            None => self.head = Some(n),
+        }
+        match next {
+            Some(x) => self.node_mut(x).prev = Some(n),
+            None => self.tail = Some(n),
+        }
+        n
+    }
+
+    fn unlink(&mut self, n: usize) {
+        let node = self.nodes[n].take().expect("linked node is in a free slot");
+        match node.prev {
+            Some(p) => self.node_mut(p).next = node.next,
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(x) => self.node_mut(x).prev = node.prev,
+            None => self.tail = node.prev,
+        }
+        self.free.push(n);
+    }
+
+    fn node(&self, n: usize) -> &Node<T> {
+        self.nodes[n]
+            .as_ref()
+            .expect("linked node is in a free slot")
+    }
+
+    fn node_mut(&mut self, n: usize) -> &mut Node<T> {
+        self.nodes[n]
+            .as_mut()
+            .expect("linked node is in a free slot")
+    }
+}
+
+impl<T> Default for TransactionLog<T> {
+    fn default() -> TransactionLog<T> {
+        TransactionLog::new_empty()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a TransactionLog<T> {
+    type Item = &'a T;
+    type IntoIter = ListIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for TransactionLog<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+// front_index is the next value to return at the front, back_index is one past the next at the back
+pub struct ListIterator<'a, T> {
+    list: &'a TransactionLog<T>,
+    front: Option<usize>,
+    front_index: usize,
+    back: Option<usize>,
+    back_index: usize,
+    remaining: u64,
+}
+
+impl<'a, T> Iterator for ListIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut node = self.list.node(self.front?);
+        if self.front_index == node.values.len() {
+            self.front = node.next;
+            self.front_index = 0;
+            node = self.list.node(self.front?);
+        }
+        self.front_index += 1;
+        self.remaining -= 1;
+        Some(&node.values[self.front_index - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ListIterator<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut node = self.list.node(self.back?);
+        if self.back_index == 0 {
+            self.back = node.prev;
+            node = self.list.node(self.back?);
+            self.back_index = node.values.len();
+        }
+        self.back_index -= 1;
+        self.remaining -= 1;
+        Some(&node.values[self.back_index])
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ListIterator<'a, T> {}
+
+pub struct IntoIter<T> {
+    list: TransactionLog<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.length as usize, Some(self.list.length as usize))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}